@@ -0,0 +1,71 @@
+// Compares the spatial hash broad phase against testing every pair of objects.
+// Run with: cargo bench --bench broad_phase
+use std::time::{Duration, Instant};
+use canvas_rust_game::engine::{Area, SpatialHash};
+use canvas_rust_game::utils::{Random, Vector};
+
+const AREA: Area = Area { width: 1400.0, height: 800.0 };
+const ITERATIONS: u32 = 20;
+
+fn create_objects( rng: &mut Random, count: usize) -> Vec<(Vector, f64)> {
+    (0..count).map( |_| {
+        let position = Vector::new( rng.random_number_max( AREA.width), rng.random_number_max( AREA.height));
+        let radius = [3.0, 10.0, 20.0, 30.0][(rng.random_number() * 4.0) as usize];
+
+        (position, radius)
+    }).collect()
+}
+
+fn collides( a: &(Vector, f64), b: &(Vector, f64)) -> bool {
+    AREA.distance( &a.0, &b.0) < a.1 + b.1
+}
+
+fn all_pairs( objects: &[(Vector, f64)]) -> usize {
+    let mut hits = 0;
+
+    for i in 0..objects.len() {
+        for j in (i + 1)..objects.len() {
+            if collides( &objects[i], &objects[j]) {
+                hits += 1;
+            }
+        }
+    }
+
+    hits
+}
+
+fn spatial_hash( grid: &mut SpatialHash, objects: &[(Vector, f64)]) -> usize {
+    grid.clear();
+    objects.iter().enumerate().for_each( |(index, (position, radius))| grid.insert( index, *position, *radius));
+
+    grid.candidate_pairs().into_iter().filter( |(i, j)| collides( &objects[*i], &objects[*j])).count()
+}
+
+fn measure( mut f: impl FnMut() -> usize) -> (Duration, usize) {
+    let start = Instant::now();
+    let mut hits = 0;
+
+    for _ in 0..ITERATIONS {
+        hits = std::hint::black_box( f());
+    }
+
+    (start.elapsed() / ITERATIONS, hits)
+}
+
+fn main() {
+    let mut rng = Random::new( 1);
+    let mut grid = SpatialHash::new( AREA, 64.0);
+
+    println!( "{:>8} {:>14} {:>14} {:>8}", "objects", "all pairs", "spatial hash", "hits");
+
+    for count in [100, 250, 500, 1000, 2000, 4000, 8000] {
+        let objects = create_objects( &mut rng, count);
+
+        let (naive_time, naive_hits) = measure( || all_pairs( &objects));
+        let (grid_time, grid_hits) = measure( || spatial_hash( &mut grid, &objects));
+
+        assert_eq!( naive_hits, grid_hits, "broad phase must not lose any collision");
+
+        println!( "{:>8} {:>14?} {:>14?} {:>8}", count, naive_time, grid_time, grid_hits);
+    }
+}
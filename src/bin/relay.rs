@@ -0,0 +1,76 @@
+// Forwards every message of a client to all other clients, for playing online on a local network.
+// Usage: cargo run --bin relay [address], the address defaults to 127.0.0.1:9001
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+use tungstenite::{Message, accept};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9001";
+// How long a client thread waits for a message before it forwards the messages of the others
+const POLL_INTERVAL: Duration = Duration::from_millis( 2);
+
+type Clients = Arc<Mutex<Vec<(usize, mpsc::Sender<Vec<u8>>)>>>;
+
+fn main() {
+    let address = std::env::args().nth( 1).unwrap_or( DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind( &address).unwrap_or_else( |e| panic!( "can not listen on {}: {}", address, e));
+    let clients: Clients = Arc::new( Mutex::new( vec![]));
+
+    println!( "Relay listening on ws://{}", address);
+
+    for (id, stream) in listener.incoming().enumerate() {
+        let Ok( stream) = stream else {
+            continue;
+        };
+
+        let clients = clients.clone();
+        thread::spawn( move || handle_client( id, stream, clients));
+    }
+}
+
+fn handle_client( id: usize, stream: TcpStream, clients: Clients) {
+    let _ = stream.set_nodelay( true);
+
+    let mut socket = match accept( stream) {
+        Ok( socket) => socket,
+        Err( e) => {
+            eprintln!( "Client {} failed to connect: {}", id, e);
+            return;
+        }
+    };
+
+    if socket.get_mut().set_read_timeout( Some( POLL_INTERVAL)).is_err() {
+        return;
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    clients.lock().unwrap().push( (id, sender));
+    println!( "Client {} connected", id);
+
+    'connection: loop {
+        match socket.read() {
+            Ok( Message::Binary( data)) => {
+                for (client, sender) in clients.lock().unwrap().iter() {
+                    if *client != id {
+                        let _ = sender.send( data.clone());
+                    }
+                }
+            }
+            Ok( Message::Close( _)) => break,
+            Ok( _) => {}
+            Err( tungstenite::Error::Io( e)) if matches!( e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err( _) => break,
+        }
+
+        while let Ok( data) = receiver.try_recv() {
+            if socket.send( Message::Binary( data)).is_err() {
+                break 'connection;
+            }
+        }
+    }
+
+    clients.lock().unwrap().retain( |(client, _)| *client != id);
+    println!( "Client {} disconnected", id);
+}
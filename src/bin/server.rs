@@ -0,0 +1,135 @@
+// Authoritative game server, clients connect with index.html?server=ws://host:9002
+// Usage: cargo run --release --bin server [address], the address defaults to 0.0.0.0:9002
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::{Message, accept};
+use canvas_rust_game::engine::{Game, NullRenderer, FIXED_DELTA_T};
+use canvas_rust_game::net::{GameServer, Transport};
+use canvas_rust_game::utils::Random;
+
+const DEFAULT_ADDRESS: &str = "0.0.0.0:9002";
+// Same as the canvas in index.html
+const GAME_WIDTH: f64 = 1400.0;
+const GAME_HEIGHT: f64 = 800.0;
+const COLORS: [&str; 6] = ["red", "blue", "green", "orange", "purple", "brown"];
+// How long a connection thread waits for a message before it sends the queued snapshots
+const POLL_INTERVAL: Duration = Duration::from_millis( 2);
+
+enum Connection {
+    Joined( usize, ChannelTransport),
+    Left( usize),
+}
+
+// Hands packets between the simulation thread and the thread of a connection
+struct ChannelTransport {
+    outgoing: mpsc::Sender<Vec<u8>>,
+    incoming: mpsc::Receiver<Vec<u8>>,
+}
+
+impl Transport for ChannelTransport {
+    fn send( &mut self, packet: &[u8]) {
+        let _ = self.outgoing.send( packet.to_vec());
+    }
+
+    fn receive( &mut self) -> Vec<Vec<u8>> {
+        self.incoming.try_iter().collect()
+    }
+}
+
+fn main() {
+    let address = std::env::args().nth( 1).unwrap_or( DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind( &address).unwrap_or_else( |e| panic!( "can not listen on {}: {}", address, e));
+    let (connections, connection_events) = mpsc::channel();
+
+    println!( "Server listening on ws://{}", address);
+
+    thread::spawn( move || {
+        for (id, stream) in listener.incoming().enumerate() {
+            let Ok( stream) = stream else {
+                continue;
+            };
+
+            let connections = connections.clone();
+            thread::spawn( move || handle_connection( id, stream, connections));
+        }
+    });
+
+    let seed = Random::from_entropy().next_u64() as u32;
+    let mut server = GameServer::new( Game::headless( GAME_WIDTH, GAME_HEIGHT, Box::new( NullRenderer), seed));
+    let mut players = std::collections::HashMap::new();
+    let tick = Duration::from_secs_f64( FIXED_DELTA_T);
+    let mut next_tick = Instant::now();
+
+    println!( "Game seed: {}", seed);
+
+    loop {
+        for event in connection_events.try_iter() {
+            match event {
+                Connection::Joined( id, transport) => {
+                    let color = COLORS[id % COLORS.len()].to_string();
+                    let player = server.add_client( Box::new( transport), color);
+                    players.insert( id, player);
+                    println!( "Connection {} joined as player {}", id, player);
+                }
+                Connection::Left( id) => {
+                    if let Some( player) = players.remove( &id) {
+                        server.remove_client( player);
+                        println!( "Player {} left", player);
+                    }
+                }
+            }
+        }
+
+        server.step();
+
+        // Catch up after a hitch instead of drifting behind the clients' clocks
+        next_tick += tick;
+        let now = Instant::now();
+        if next_tick > now {
+            thread::sleep( next_tick - now);
+        }
+    }
+}
+
+fn handle_connection( id: usize, stream: TcpStream, connections: mpsc::Sender<Connection>) {
+    let _ = stream.set_nodelay( true);
+
+    let mut socket = match accept( stream) {
+        Ok( socket) => socket,
+        Err( e) => {
+            eprintln!( "Connection {} failed: {}", id, e);
+            return;
+        }
+    };
+
+    if socket.get_mut().set_read_timeout( Some( POLL_INTERVAL)).is_err() {
+        return;
+    }
+
+    let (to_game, incoming) = mpsc::channel();
+    let (outgoing, from_game) = mpsc::channel();
+    if connections.send( Connection::Joined( id, ChannelTransport { outgoing, incoming })).is_err() {
+        return;
+    }
+
+    'connection: loop {
+        match socket.read() {
+            Ok( Message::Binary( data)) => { let _ = to_game.send( data); }
+            Ok( Message::Close( _)) => break,
+            Ok( _) => {}
+            Err( tungstenite::Error::Io( e)) if matches!( e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err( _) => break,
+        }
+
+        while let Ok( data) = from_game.try_recv() {
+            if socket.send( Message::Binary( data)).is_err() {
+                break 'connection;
+            }
+        }
+    }
+
+    let _ = connections.send( Connection::Left( id));
+}
@@ -1,31 +1,77 @@
-use web_sys::{ CanvasRenderingContext2d, HtmlImageElement};
+use serde::{Serialize, Deserialize};
 use crate::utils::Vector;
-use crate::engine::{GameObject, GameObjectType, Area};
-use crate::utils::random_number;
+use crate::engine::collision::{LAYER_ASTEROID, LAYER_BULLET, LAYER_ROCKET, LAYER_SAUCER_BULLET};
+use crate::engine::{GameObject, ObjectSnapshot, GameObjectType, Area, EventQueue, GameEvent, Spawn, Renderer, Sprite, Hull, interpolate};
+use crate::utils::Random;
 use std::any::Any;
-use std::rc::Rc;
-use std::cell::RefCell;
-use crate::GameObjectFactory;
 
-#[derive(PartialEq, Eq)]
+// Speed range of the two fragments of a split asteroid of mass 1, heavier fragments get the same energy and are slower
+const MIN_FRAGMENT_SPEED: f64 = 30.0;
+const MAX_FRAGMENT_SPEED: f64 = 80.0;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum AsteroidSize {
     Small,
     Medium,
     Large,
 }
 
+impl AsteroidSize {
+    pub fn radius( &self) -> f64 {
+        match self {
+            AsteroidSize::Small => 10.0,
+            AsteroidSize::Medium => 20.0,
+            AsteroidSize::Large => 30.0,
+        }
+    }
+
+    // Every asteroid splits into two of half its mass
+    pub fn mass( &self) -> f64 {
+        match self {
+            AsteroidSize::Small => 1.0,
+            AsteroidSize::Medium => 2.0,
+            AsteroidSize::Large => 4.0,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Asteroid {
     pub size: AsteroidSize,
     pub expired: bool,
     pub position: Vector,
+    pub previous_position: Vector,
     pub rotation: f64,
     pub speed: Vector,
     pub acc: Vector,
     pub radius: f64,
-    pub image: HtmlImageElement,
+    pub image: Sprite,
+    pub hull: Hull,
  }
 
 impl Asteroid {
+    // Two fragments flying apart sideways. They get opposite kicks, so together they carry exactly the momentum of this asteroid.
+    fn split( &self, fragment: AsteroidSize, rng: &mut Random, events: &mut EventQueue) {
+        let direction = if self.speed.length() > 0.0 { self.speed.normalize() } else { Vector::new( 1.0, 0.0) };
+        let angle = (rng.random_number() - 0.5) * std::f64::consts::PI / 2.0;
+        let sideways = Vector { x: -direction.y, y: direction.x }.rotate( angle);
+
+        let fragment_speed = MIN_FRAGMENT_SPEED + rng.random_number() * (MAX_FRAGMENT_SPEED - MIN_FRAGMENT_SPEED);
+        let kick = sideways.scale( fragment_speed / fragment.mass().sqrt());
+
+        // Side by side instead of on top of each other, so they do not bump into each other right away
+        let offset = sideways.scale( fragment.radius());
+
+        for side in [1.0, -1.0] {
+            let position = self.position.add( &offset.scale( side));
+            let speed = self.speed.add( &kick.scale( side));
+
+            match fragment {
+                AsteroidSize::Medium => events.spawn( Spawn::AsteroidMedium { position, speed }),
+                _ => events.spawn( Spawn::AsteroidSmall { position, speed }),
+            }
+        }
+    }
 }
 
 impl GameObject for Asteroid {
@@ -41,6 +87,10 @@ impl GameObject for Asteroid {
         return GameObjectType::Asteroid;
     }
 
+    fn snapshot( &self) -> ObjectSnapshot {
+        ObjectSnapshot::Asteroid( self.clone())
+    }
+
     fn current_position( &self) -> Vector {
         self.position
     }
@@ -53,7 +103,7 @@ impl GameObject for Asteroid {
         return self.expired;
     }
 
-    fn move_t( &mut self, delta_t: f64, game_area: Area) {
+    fn move_t( &mut self, delta_t: f64, game_area: Area, _events: &mut EventQueue) {
         self.speed = self.speed.add( &self.acc.scale(delta_t));
         self.position = self.position.add( &self.speed.scale(delta_t));
 
@@ -74,53 +124,66 @@ impl GameObject for Asteroid {
         }
     }
 
-    fn render( &self, ctx: &CanvasRenderingContext2d) {
-        ctx.save();
-        ctx.translate(self.position.x, self.position.y).unwrap();          // Move to sprite position
-        ctx.rotate( self.rotation).unwrap();        // Rotate around that point
-        ctx.draw_image_with_html_image_element_and_dw_and_dh(
-            &self.image,
-            - (self.image.width() as f64 / 2.0),
-            - (self.image.height() as f64 / 2.0),
-            self.image.width() as f64,
-            self.image.height() as f64,
-        ).unwrap();
-        ctx.restore();
+    fn store_previous_state( &mut self) {
+        self.previous_position = self.position;
+    }
+
+    fn previous_position( &self) -> Vector {
+        self.previous_position
+    }
+
+    fn render( &self, renderer: &mut dyn Renderer, interpolation: f64) {
+        let position = interpolate( self.previous_position, self.position, interpolation);
+
+        renderer.save();
+        renderer.translate(position.x, position.y);          // Move to sprite position
+        renderer.rotate( self.rotation);        // Rotate around that point
+        renderer.draw_sprite( &self.image, 0.0, 0.0);
+        renderer.restore();
     }
 
     fn radius( &self) -> f64 {
         return self.radius;
     }
 
-    fn collision_with( &mut self, objtype: GameObjectType, objfactory: &GameObjectFactory) -> Vec<Rc<RefCell<dyn GameObject>>> {
+    fn collision_layer( &self) -> u32 {
+        LAYER_ASTEROID
+    }
+
+    fn collision_mask( &self) -> u32 {
+        LAYER_ASTEROID | LAYER_ROCKET | LAYER_BULLET | LAYER_SAUCER_BULLET
+    }
+
+    fn hull( &self) -> Option<Vec<Vector>> {
+        Some( self.hull.rotated( self.rotation))
+    }
+
+    fn mass( &self) -> f64 {
+        self.size.mass()
+    }
+
+    fn velocity( &self) -> Vector {
+        self.speed
+    }
+
+    fn push( &mut self, velocity_change: Vector, offset: Vector) {
+        self.speed = self.speed.add( &velocity_change);
+        self.position = self.position.add( &offset);
+    }
+
+    fn collision_with( &mut self, objtype: GameObjectType, rng: &mut Random, events: &mut EventQueue) {
         if objtype == GameObjectType::Bullet || objtype == GameObjectType::Rocket {
-            let mut result = Vec::new();
-
-            if self.size == AsteroidSize::Large {
-                let base_dir = self.speed.normalize();
-                let perp = Vector { x: -base_dir.y, y: base_dir.x };
-                for _ in 0..2 {
-                    let angle = (random_number() - 0.5) * std::f64::consts::PI / 2.0;
-                    let impulse = perp.rotate(angle).scale((random_number() * 50.0 + 30.0) * 0.5); // mass scaling: quarter mass => half speed
-                    let new_speed = self.speed.add(&impulse);
-                    result.push(objfactory.create_asteroid_medium(self.position, new_speed));
-                }
-            } 
-            else if self.size == AsteroidSize::Medium {
-                let base_dir = self.speed.normalize();
-                let perp = Vector { x: -base_dir.y, y: base_dir.x };
-                for _ in 0..2 {
-                    let angle = (random_number() - 0.5) * std::f64::consts::PI / 2.0;
-                    let impulse = perp.rotate(angle).scale(random_number() * 50.0 + 30.0); // slightly faster for smaller fragments
-                    let new_speed = self.speed.add(&impulse);
-                    result.push(objfactory.create_asteroid_small(self.position, new_speed));
-                }
+            match self.size {
+                AsteroidSize::Large => self.split( AsteroidSize::Medium, rng, events),
+                AsteroidSize::Medium => self.split( AsteroidSize::Small, rng, events),
+                AsteroidSize::Small => {}
             }
 
             self.expire();
 
-            return result;
+            events.spawn( Spawn::PowerUpDrop { position: self.position });
+
+            events.emit( GameEvent::Destroyed { objtype: GameObjectType::Asteroid, position: self.position });
         }
-        vec![]
     }
 }
@@ -1,19 +1,20 @@
-use web_sys::CanvasRenderingContext2d;
-use crate::utils::Vector;
-use crate::engine::{GameObject, GameObjectType, Area};
-use crate::components::Rocket;
+use serde::{Serialize, Deserialize};
+use crate::utils::{Vector, Random};
+use crate::engine::collision::{LAYER_ASTEROID, LAYER_BULLET, LAYER_ROCKET, LAYER_SAUCER, LAYER_SAUCER_BULLET};
+use crate::engine::{GameObject, ObjectSnapshot, GameObjectType, Area, EventQueue, GameEvent, Spawn, Renderer, interpolate};
 use std::any::Any;
-use std::rc::Rc;
-use std::cell::RefCell;
-use crate::GameObjectFactory;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Bullet {
     pub expired: bool,
     pub start_position: Vector,
     pub position: Vector,
+    pub previous_position: Vector,
     pub speed: Vector,
     pub color: String,
-    pub rocket: *mut Rocket,
+    // None for bullets of saucers, which score for nobody
+    pub player: Option<usize>,
+    pub team: Option<usize>,
  }
 
 impl GameObject for Bullet {
@@ -30,6 +31,10 @@ impl GameObject for Bullet {
         return GameObjectType::Bullet;
     }
 
+    fn snapshot( &self) -> ObjectSnapshot {
+        ObjectSnapshot::Bullet( self.clone())
+    }
+
     fn current_position(&self) -> Vector {
         return self.position;
     }
@@ -42,34 +47,76 @@ impl GameObject for Bullet {
         return self.expired || (self.position.distance( &self.start_position) > 700.0);
     }
 
-    fn move_t(&mut self, delta_t: f64, _game_area: Area) {
+    fn move_t(&mut self, delta_t: f64, game_area: Area, _events: &mut EventQueue) {
         self.position = self.position.add( &self.speed.scale(delta_t));
+
+        // Bullets do not wrap around the edges, otherwise they would hit things on the other side without being seen there
+        if !game_area.contains( &self.position) {
+            self.expire();
+        }
+    }
+
+    fn store_previous_state( &mut self) {
+        self.previous_position = self.position;
     }
 
-    fn render(&self, ctx: &CanvasRenderingContext2d) {
-        ctx.begin_path();
-        ctx.arc( self.position.x, self.position.y, 3.0, 0.0, std::f64::consts::PI * 2.0).unwrap();
-        ctx.set_fill_style_str( &self.color);
-    
-        ctx.fill();
+    fn previous_position( &self) -> Vector {
+        self.previous_position
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, interpolation: f64) {
+        let position = interpolate( self.previous_position, self.position, interpolation);
+
+        renderer.fill_circle( position.x, position.y, 3.0, &self.color);
     }
 
     fn radius( &self) -> f64 {
         return 3.0;
     }
 
-    fn collision_with(&mut self, objtype: GameObjectType, objfactory: &GameObjectFactory) -> Vec<Rc<RefCell<dyn GameObject>>> {
+    fn collision_layer( &self) -> u32 {
+        // Saucers do not shoot each other down, neither side of the pair may collide
+        if self.player.is_none() {
+            return LAYER_SAUCER_BULLET;
+        }
+
+        LAYER_BULLET
+    }
+
+    fn collision_mask( &self) -> u32 {
+        if self.player.is_none() {
+            return LAYER_ASTEROID | LAYER_ROCKET;
+        }
+
+        LAYER_ASTEROID | LAYER_ROCKET | LAYER_SAUCER
+    }
 
-        if objtype == GameObjectType::Asteroid  || objtype == GameObjectType::Rocket {
+    fn team( &self) -> Option<usize> {
+        self.team
+    }
+
+    // Hits a teammate without scoring for it
+    fn collision_with_teammate( &mut self, objtype: GameObjectType, _rng: &mut Random, events: &mut EventQueue) {
+        if objtype == GameObjectType::Rocket {
             self.expire();
 
-            unsafe {
-                (*self.rocket).score += 100;
+            events.emit( GameEvent::Destroyed { objtype: GameObjectType::Bullet, position: self.position });
+            events.spawn( Spawn::Explosion { position: self.position });
+        }
+    }
+
+    fn collision_with(&mut self, objtype: GameObjectType, _rng: &mut Random, events: &mut EventQueue) {
+
+        if objtype == GameObjectType::Asteroid  || objtype == GameObjectType::Rocket || objtype == GameObjectType::Saucer {
+            self.expire();
+
+            // The game mode decides what the hit is worth
+            if let Some( player) = self.player {
+                events.emit( GameEvent::Hit { player, target: objtype });
             }
 
-            return vec![objfactory.create_explosion(self.position)];
+            events.emit( GameEvent::Destroyed { objtype: GameObjectType::Bullet, position: self.position });
+            events.spawn( Spawn::Explosion { position: self.position });
         }
-
-        vec![]
     }
 }
@@ -1,18 +1,15 @@
-use web_sys::{ CanvasRenderingContext2d};
-use crate::utils::Vector;
-use crate::engine::{GameObject, GameObjectType, Area};
+use serde::{Serialize, Deserialize};
+use crate::utils::{Vector, Random};
+use crate::engine::collision::{LAYER_NONE};
+use crate::engine::{GameObject, ObjectSnapshot, GameObjectType, Area, EventQueue, Renderer, Spawn};
 use std::any::Any;
-use std::rc::Rc;
-use std::cell::RefCell;
-use crate::Game;
-use crate::GameObjectFactory;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Countdown {
     pub time: f64,
     pub position: Vector,
     pub count : i32,
     pub text: String,
-    pub game: *mut Game,
  }
 
 impl Countdown {
@@ -34,6 +31,10 @@ impl GameObject for Countdown {
         return GameObjectType::Countdown;
     }
 
+    fn snapshot( &self) -> ObjectSnapshot {
+        ObjectSnapshot::Countdown( self.clone())
+    }
+
     fn current_position(&self) -> Vector {
         self.position
     }
@@ -46,37 +47,38 @@ impl GameObject for Countdown {
 
     }
 
-    fn move_t(&mut self, delta_t: f64, _game_area: Area) {
+    fn move_t(&mut self, delta_t: f64, _game_area: Area, events: &mut EventQueue) {
         self.time += delta_t;
 
         if self.time > 1.0 {
             self.count -= 1;
             self.time = 0.0;
             self.format_count();
-        }
 
-        if self.count == -1 {
-            unsafe {
-                (*self.game).spawn_asteroids();
+            if self.count == -1 {
+                events.spawn( Spawn::Asteroids);
             }
         }
     }
 
-    fn render(&self, ctx: &CanvasRenderingContext2d) {
-        ctx.set_font("72px sans-serif");
-        ctx.set_fill_style_str( "black");
-        ctx.set_text_align("left");
-        ctx.set_text_baseline("middle");
-        ctx.set_global_alpha( 1.0 - self.time);
-        ctx.fill_text(&self.text, self.position.x, self.position.y).unwrap();
-        ctx.set_global_alpha(1.0);
+    fn render(&self, renderer: &mut dyn Renderer, _interpolation: f64) {
+        renderer.set_alpha( 1.0 - self.time);
+        renderer.fill_text(&self.text, self.position.x, self.position.y, "72px sans-serif", "black");
+        renderer.set_alpha(1.0);
     }
 
     fn radius( &self) -> f64 {
         return 10.0;
     }
 
-    fn collision_with(&mut self, _objtype: GameObjectType, _objfactory: &GameObjectFactory) -> Vec<Rc<RefCell<dyn GameObject>>> {
-        vec![]
+    fn collision_layer( &self) -> u32 {
+        LAYER_NONE
+    }
+
+    fn collision_mask( &self) -> u32 {
+        LAYER_NONE
+    }
+
+    fn collision_with(&mut self, _objtype: GameObjectType, _rng: &mut Random, _events: &mut EventQueue) {
     }
 }
@@ -1,14 +1,15 @@
-use web_sys::{ CanvasRenderingContext2d, HtmlImageElement};
-use crate::utils::Vector;
-use crate::engine::{GameObject, GameObjectType, Area};
-use crate::GameObjectFactory;
+use serde::{Serialize, Deserialize};
+use crate::utils::{Vector, Random};
+use crate::engine::collision::{LAYER_NONE};
+use crate::engine::{GameObject, ObjectSnapshot, GameObjectType, Area, EventQueue, Renderer, Sprite};
 use std::any::Any;
-use std::rc::Rc;
-use std::cell::RefCell;
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Explosion {
     pub time: f64,
     pub position: Vector,
-    pub image: HtmlImageElement,
+    pub image: Sprite,
+    // Final size relative to the sprite
+    pub size: f64,
  }
 
 impl GameObject for Explosion {
@@ -25,6 +26,10 @@ impl GameObject for Explosion {
         return GameObjectType::Explosion;
     }
 
+    fn snapshot( &self) -> ObjectSnapshot {
+        ObjectSnapshot::Explosion( self.clone())
+    }
+
     fn current_position(&self) -> Vector {
         self.position
     }
@@ -37,30 +42,31 @@ impl GameObject for Explosion {
 
     }
 
-    fn move_t(&mut self, delta_t: f64, _game_area: Area) {
+    fn move_t(&mut self, delta_t: f64, _game_area: Area, _events: &mut EventQueue) {
         self.time += delta_t;
     }
 
-    fn render(&self, ctx: &CanvasRenderingContext2d) {
-        ctx.save();
-        ctx.translate(self.position.x, self.position.y).unwrap();          // Move to sprite position
-        let _ = ctx.scale( self.time / 1.0, self.time / 1.0);
-        ctx.draw_image_with_html_image_element_and_dw_and_dh(
-            &self.image,
-            - (self.image.width() as f64 / 2.0),
-            - (self.image.height() as f64 / 2.0),
-            self.image.width() as f64,
-            self.image.height() as f64,
-        ).unwrap();
-        ctx.restore();
+    fn render(&self, renderer: &mut dyn Renderer, _interpolation: f64) {
+        renderer.save();
+        renderer.translate(self.position.x, self.position.y);          // Move to sprite position
+        renderer.scale( self.time * self.size, self.time * self.size);
+        renderer.draw_sprite( &self.image, 0.0, 0.0);
+        renderer.restore();
     }
 
     fn radius( &self) -> f64 {
         return 10.0;
     }
 
+    fn collision_layer( &self) -> u32 {
+        LAYER_NONE
+    }
+
+    fn collision_mask( &self) -> u32 {
+        LAYER_NONE
+    }
+
 
-    fn collision_with(&mut self, _objtype: GameObjectType, _objfactory: &GameObjectFactory) -> Vec<Rc<RefCell<dyn GameObject>>> {
-        vec![]
+    fn collision_with(&mut self, _objtype: GameObjectType, _rng: &mut Random, _events: &mut EventQueue) {
     }
 }
@@ -1,38 +1,47 @@
-use web_sys::{HtmlImageElement};
 use crate::utils::Vector;
 use std::rc::Rc;
 use std::cell::RefCell;
 use crate::components::asteroid::Asteroid;
 use crate::components::asteroid::AsteroidSize;
 use crate::components::explosion::Explosion;
-use crate::components::rocket::Rocket;
+use crate::components::rocket::{Rocket, START_LIVES};
 use crate::components::countdown::Countdown;
 use crate::components::bullet::Bullet;
+use crate::components::saucer::{Saucer, SaucerSize, ZIGZAG_SPEED};
+use crate::components::power_up::{PowerUp, PowerUpKind};
 use crate::GameObject;
-use crate::Game;
+use crate::engine::{Sprite, Wave, Hull};
 use crate::Area;
-use crate::utils::random_number;
-use crate::utils::random_number_max;
+use crate::utils::Random;
 use crate::utils::ZERO;
 
+// Collision outlines of the bundled sprites, in sprite pixels around the center with the rocket pointing up
+const ROCKET_OUTLINE: [(f64, f64); 5] = [(0.0, -20.0), (7.0, -6.0), (10.0, 17.0), (-10.0, 17.0), (-7.0, -6.0)];
+// Lumpy octagon, as fraction of the radius of the sprite
+const ASTEROID_OUTLINE: [(f64, f64); 8] = [(0.0, -0.95), (0.7, -0.7), (0.98, 0.0), (0.65, 0.68), (0.0, 0.96), (-0.68, 0.66), (-0.94, 0.0), (-0.66, -0.7)];
+
+fn hull( outline: &[(f64, f64)], scale: f64) -> Hull {
+    Hull::new( outline.iter().map( |(x, y)| Vector::new( x * scale, y * scale)).collect())
+}
+
 pub struct GameObjectFactory {
-    asteroid_small_image: HtmlImageElement,
-    asteroid_medium_image: HtmlImageElement,
-    asteroid_large_image: HtmlImageElement,
-    explosion_image: HtmlImageElement,
-    rocket_thrust_on_image: HtmlImageElement,
-    rocket_thrust_off_image: HtmlImageElement,
+    asteroid_small_image: Sprite,
+    asteroid_medium_image: Sprite,
+    asteroid_large_image: Sprite,
+    explosion_image: Sprite,
+    rocket_thrust_on_image: Sprite,
+    rocket_thrust_off_image: Sprite,
 }
 
 impl GameObjectFactory {
 
     pub fn new(
-        asteroid_small_image: HtmlImageElement,
-        asteroid_medium_image: HtmlImageElement,
-        asteroid_large_image: HtmlImageElement,
-        explosion_image: HtmlImageElement,
-        rocket_thrust_on_image: HtmlImageElement,
-        rocket_thrust_off_image: HtmlImageElement,
+        asteroid_small_image: Sprite,
+        asteroid_medium_image: Sprite,
+        asteroid_large_image: Sprite,
+        explosion_image: Sprite,
+        rocket_thrust_on_image: Sprite,
+        rocket_thrust_off_image: Sprite,
     ) -> Self {
         GameObjectFactory {
             asteroid_small_image,
@@ -42,18 +51,32 @@ impl GameObjectFactory {
             rocket_thrust_on_image,
             rocket_thrust_off_image,
         }
-    }    
+    }
+
+    // Sprite handles with the dimensions of the bundled assets, for renderers that do not draw images
+    pub fn with_placeholder_sprites() -> Self {
+        Self::new(
+            Sprite { id: 0, width: 20.0, height: 20.0 },
+            Sprite { id: 1, width: 35.0, height: 36.0 },
+            Sprite { id: 2, width: 50.0, height: 52.0 },
+            Sprite { id: 3, width: 50.0, height: 50.0 },
+            Sprite { id: 4, width: 50.0, height: 50.0 },
+            Sprite { id: 5, width: 50.0, height: 50.0 },
+        )
+    }
 
     pub fn create_asteroid_small( &self, position: Vector, speed: Vector) -> Rc<RefCell<dyn GameObject>> {
         Rc::new( RefCell::new( Asteroid {
             size: AsteroidSize::Small,
             expired: false,
             position: position,
+            previous_position: position,
             rotation: 0.0,
             speed: speed,
             acc: Vector::new(0.0, 0.0),
-            radius: 10.0,
-            image: self.asteroid_small_image.clone(),
+            radius: AsteroidSize::Small.radius(),
+            image: self.asteroid_small_image,
+            hull: hull( &ASTEROID_OUTLINE, self.asteroid_small_image.width.min( self.asteroid_small_image.height) / 2.0),
         }))
     }
 
@@ -62,11 +85,13 @@ impl GameObjectFactory {
             size: AsteroidSize::Medium,
             expired: false,
             position: position,
+            previous_position: position,
             rotation: 0.0,
             speed: speed,
             acc: Vector::new(0.0, 0.0),
-            radius: 20.0,
-            image: self.asteroid_medium_image.clone(),
+            radius: AsteroidSize::Medium.radius(),
+            image: self.asteroid_medium_image,
+            hull: hull( &ASTEROID_OUTLINE, self.asteroid_medium_image.width.min( self.asteroid_medium_image.height) / 2.0),
         }))
     }
 
@@ -75,11 +100,13 @@ impl GameObjectFactory {
             size: AsteroidSize::Large,
             expired: false,
             position: position,
+            previous_position: position,
             rotation: 0.0,
             speed: speed,
             acc: Vector::new(0.0, 0.0),
-            radius: 30.0,
-            image: self.asteroid_large_image.clone(),
+            radius: AsteroidSize::Large.radius(),
+            image: self.asteroid_large_image,
+            hull: hull( &ASTEROID_OUTLINE, self.asteroid_large_image.width.min( self.asteroid_large_image.height) / 2.0),
         }))
     }
 
@@ -87,49 +114,77 @@ impl GameObjectFactory {
         Rc::new( RefCell::new( Explosion {
             time: 0.0f64,
             position: position,
-            image: self.explosion_image.clone(),
+            image: self.explosion_image,
+            size: 1.0,
         }))
     }
 
-    pub fn create_asteroids( &self, nof : i32, area: Area, max_speed: f64) -> Vec<Rc<RefCell<dyn GameObject>>> {
-        let mut i = 0;
-        let mut asteroids = vec![];
+    // When a rocket is destroyed
+    pub fn create_big_explosion( &self, position: Vector) -> Rc<RefCell<dyn GameObject>> {
+        Rc::new( RefCell::new( Explosion {
+            time: 0.0f64,
+            position: position,
+            image: self.explosion_image,
+            size: 3.0,
+        }))
+    }
 
-        while i < nof  {
-            let position = Vector { x: random_number_max( area.width as f64), y: random_number_max( area.height as f64) };
-            let speed = Vector { x: max_speed * random_number(), y: max_speed * random_number()};
+    // Asteroids of a wave, sizes, positions and speeds are up to the wave
+    pub fn create_asteroids( &self, rng: &mut Random, wave: &Wave, area: Area) -> Vec<Rc<RefCell<dyn GameObject>>> {
+        let mut asteroids = vec![];
 
-            asteroids.push( self.create_asteroid_large( position, speed));
+        for _ in 0..wave.asteroids {
+            let size = wave.random_size( rng);
+            let position = wave.random_position( rng, area);
+            let speed = wave.random_speed( rng);
 
-            i+=1;
+            let asteroid = match size {
+                AsteroidSize::Large => self.create_asteroid_large( position, speed),
+                AsteroidSize::Medium => self.create_asteroid_medium( position, speed),
+                AsteroidSize::Small => self.create_asteroid_small( position, speed),
+            };
+            asteroids.push( asteroid);
         }
 
         return asteroids;
     }
 
-    pub fn create_rocket( &self, pos: Vector, score_position: Vector, color: String) -> Rc<RefCell<dyn GameObject>> {
+    pub fn create_rocket( &self, player: usize, pos: Vector, score_position: Vector, color: String) -> Rc<RefCell<dyn GameObject>> {
         Rc::new( RefCell::new( Rocket {
+            player: player,
             score: 0,
             damage: 0,
             score_pos: score_position,
             position: pos,
+            previous_position: pos,
             rotation: 0.0,
+            previous_rotation: 0.0,
             speed: ZERO,
             acc: ZERO,
             thrust: 0.0,
-            sprite_on: self.rocket_thrust_on_image.clone(),
-            sprite_off: self.rocket_thrust_off_image.clone(),
+            sprite_on: self.rocket_thrust_on_image,
+            sprite_off: self.rocket_thrust_off_image,
+            hull: hull( &ROCKET_OUTLINE, 1.0),
             last_shot: 0.0,
             shield_on: false,
             shield_time: 0.0,
             bullet_color: color,
+            team: None,
             burst_time: 0.0,
+            lives: Some( START_LIVES),
+            destroyed: false,
+            respawn_time: 0.0,
+            invulnerable_time: 0.0,
+            hyperspace_cooldown: 0.0,
+            warp_time: 0.0,
+            warp_fails: false,
+            rapid_fire_time: 0.0,
+            triple_shot_time: 0.0,
         }))
     }
 
-    pub fn create_countdown( &self, game: *mut Game, position: Vector, count: i32) -> Rc<RefCell<dyn GameObject>> {
+    pub fn create_countdown( &self, position: Vector, count: i32) -> Rc<RefCell<dyn GameObject>> {
         Rc::new( RefCell::new( Countdown {
-            game: game,
             position: position,
             time: 3.0,
             count: count,
@@ -137,14 +192,40 @@ impl GameObjectFactory {
         }))
     }
 
-    pub fn create_bullet( &self, rocket: *mut Rocket, position: Vector, speed: Vector, color: String) -> Rc<RefCell<dyn GameObject>> {
+    pub fn create_power_up( &self, position: Vector, kind: PowerUpKind) -> Rc<RefCell<dyn GameObject>> {
+        Rc::new( RefCell::new( PowerUp {
+            kind: kind,
+            position: position,
+            time: 0.0,
+            taken: false,
+        }))
+    }
+
+    // Enters at the left or right edge and crosses the screen
+    pub fn create_saucer( &self, size: SaucerSize, position: Vector, from_left: bool) -> Rc<RefCell<dyn GameObject>> {
+        let direction = if from_left { 1.0 } else { -1.0 };
+
+        Rc::new( RefCell::new( Saucer {
+            size: size,
+            expired: false,
+            position: position,
+            previous_position: position,
+            speed: Vector::new( direction * size.speed(), ZIGZAG_SPEED),
+            zigzag_time: 0.0,
+            fire_time: 0.0,
+        }))
+    }
+
+    pub fn create_bullet( &self, player: Option<usize>, team: Option<usize>, position: Vector, speed: Vector, color: String) -> Rc<RefCell<dyn GameObject>> {
         Rc::new( RefCell::new( Bullet {
             expired: false,
             start_position: position,
             position: position,
+            previous_position: position,
             speed: speed,
             color: color,
-            rocket: rocket,
+            player: player,
+            team: team,
         }))
     }
 }
@@ -3,7 +3,19 @@ mod bullet;
 mod asteroid;
 mod explosion;
 mod countdown;
+mod power_up;
+mod saucer;
 mod game_object_factory;
 
 pub use rocket::Rocket;
+pub use rocket::START_LIVES;
+pub use bullet::Bullet;
+pub use asteroid::Asteroid;
+pub use asteroid::AsteroidSize;
+pub use explosion::Explosion;
+pub use countdown::Countdown;
+pub use power_up::PowerUp;
+pub use power_up::PowerUpKind;
+pub use saucer::Saucer;
+pub use saucer::SaucerSize;
 pub use game_object_factory::GameObjectFactory;
@@ -0,0 +1,125 @@
+use serde::{Serialize, Deserialize};
+use crate::utils::{Vector, Random};
+use crate::engine::collision::{LAYER_POWER_UP, LAYER_ROCKET};
+use crate::engine::{GameObject, ObjectSnapshot, GameObjectType, Area, EventQueue, Renderer};
+use std::any::Any;
+
+// Seconds a power-up floats around before it vanishes
+const LIFETIME: f64 = 10.0;
+// Blinks during the last seconds of its lifetime
+const BLINK_TIME: f64 = 2.0;
+const RADIUS: f64 = 12.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    RapidFire,
+    TripleShot,
+    ShieldRecharge,
+    Repair,
+    ExtraLife,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 5] = [
+        PowerUpKind::RapidFire,
+        PowerUpKind::TripleShot,
+        PowerUpKind::ShieldRecharge,
+        PowerUpKind::Repair,
+        PowerUpKind::ExtraLife,
+    ];
+
+    pub fn random( rng: &mut Random) -> PowerUpKind {
+        let index = rng.random_number_max( Self::ALL.len() as f64) as usize;
+
+        Self::ALL[index.min( Self::ALL.len() - 1)]
+    }
+
+    // Letter and color of the icon, on the power-up itself and in the HUD
+    pub fn icon( &self) -> (&'static str, &'static str) {
+        match self {
+            PowerUpKind::RapidFire => ("R", "orange"),
+            PowerUpKind::TripleShot => ("T", "purple"),
+            PowerUpKind::ShieldRecharge => ("S", "deepskyblue"),
+            PowerUpKind::Repair => ("+", "green"),
+            PowerUpKind::ExtraLife => ("1UP", "gold"),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub position: Vector,
+    pub time: f64,
+    pub taken: bool,
+}
+
+impl GameObject for PowerUp {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn get_type( &self) -> GameObjectType {
+        return GameObjectType::PowerUp;
+    }
+
+    fn snapshot( &self) -> ObjectSnapshot {
+        ObjectSnapshot::PowerUp( self.clone())
+    }
+
+    fn current_position(&self) -> Vector {
+        self.position
+    }
+
+    fn expire( &mut self) {
+        self.taken = true;
+    }
+
+    fn is_expired( &self) -> bool {
+        return self.taken || self.time > LIFETIME;
+    }
+
+    fn move_t(&mut self, delta_t: f64, _game_area: Area, _events: &mut EventQueue) {
+        self.time += delta_t;
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _interpolation: f64) {
+        let remaining = LIFETIME - self.time;
+        if remaining < BLINK_TIME && (remaining * 8.0) as i32 % 2 == 1 {
+            return;
+        }
+
+        let (letter, color) = self.kind.icon();
+        renderer.fill_circle( self.position.x, self.position.y, RADIUS, color);
+        renderer.fill_text( letter, self.position.x - 5.0, self.position.y, "12px sans-serif", "white");
+    }
+
+    fn radius( &self) -> f64 {
+        RADIUS
+    }
+
+    fn collision_layer( &self) -> u32 {
+        LAYER_POWER_UP
+    }
+
+    fn collision_mask( &self) -> u32 {
+        LAYER_ROCKET
+    }
+
+    fn collision_with(&mut self, _objtype: GameObjectType, _rng: &mut Random, _events: &mut EventQueue) {
+    }
+
+    fn take_power_up( &mut self) -> Option<PowerUpKind> {
+        if self.is_expired() {
+            return None;
+        }
+
+        self.taken = true;
+
+        Some( self.kind)
+    }
+}
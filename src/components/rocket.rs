@@ -1,39 +1,90 @@
-use web_sys::{ CanvasRenderingContext2d, HtmlImageElement};
-use crate::utils::Vector;
+use serde::{Serialize, Deserialize};
+use crate::utils::{Vector, Random, ZERO};
 use std::f64::consts::FRAC_PI_2;
-use crate::engine::{GameObject, GameObjectType, Area, GamepadState};
-use std::f64::consts::PI;
-use crate::components::GameObjectFactory;
+use crate::engine::collision::{LAYER_ASTEROID, LAYER_BULLET, LAYER_ROCKET, LAYER_POWER_UP, LAYER_SAUCER, LAYER_SAUCER_BULLET, LAYER_NONE};
+use crate::components::PowerUpKind;
+use crate::engine::{GameObject, ObjectSnapshot, GameObjectType, Area, EventQueue, GameEvent, Spawn, GamepadState, Renderer, Sprite, Hull, interpolate, team_color};
 use std::any::Any;
-use std::rc::Rc;
-use std::cell::RefCell;
 
 const MAX_SHIELD_TIME: f64 = 2.0;
 const MAX_SHIELD_STROKE_WIDTH: f64 = 6.0;
 const MAX_BURST_TIME: f64 = 2.5;
-
+// Radians per second at full stick deflection
+const ROTATION_SPEED: f64 = 6.0;
+// Destroyed once the damage reaches this, e.g. ten asteroids or two rockets
+pub const MAX_HIT_POINTS: i32 = 1000;
+pub const START_LIVES: i32 = 3;
+// Seconds between destruction and respawn
+const RESPAWN_TIME: f64 = 2.0;
+// Seconds after a respawn during which nothing can hit the rocket
+const INVULNERABLE_TIME: f64 = 2.0;
+const BLINK_INTERVAL: f64 = 0.1;
+// Seconds between two hyperspace jumps
+const HYPERSPACE_COOLDOWN: f64 = 3.0;
+// Seconds the rocket needs to materialize after a jump
+const WARP_TIME: f64 = 0.5;
+// Chance that the rocket explodes when it materializes
+const HYPERSPACE_FAILURE_CHANCE: f64 = 0.05;
+// Seconds between two shots, normally and with rapid fire
+const SHOT_INTERVAL: f64 = 0.2;
+const RAPID_FIRE_INTERVAL: f64 = 0.08;
+// Seconds the timed power-ups last
+const POWER_UP_TIME: f64 = 10.0;
+// Angle between the bullets of a triple shot
+const TRIPLE_SHOT_SPREAD: f64 = 0.2;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Rocket {
+    pub player: usize,
     pub score: i32,
     pub damage: i32,
     pub score_pos: Vector,
     pub position: Vector,
+    pub previous_position: Vector,
     pub rotation: f64,
+    pub previous_rotation: f64,
     pub speed: Vector,
     pub acc: Vector,
     pub thrust: f64,
-    pub sprite_on: HtmlImageElement,
-    pub sprite_off: HtmlImageElement,
+    pub sprite_on: Sprite,
+    pub sprite_off: Sprite,
+    pub hull: Hull,
     pub last_shot: f64,
     pub shield_on: bool,
     pub shield_time: f64,
     pub bullet_color: String,
-    pub burst_time : f64
+    // None when the rocket plays for itself
+    pub team: Option<usize>,
+    pub burst_time : f64,
+    // None when the game mode respawns rockets forever
+    pub lives: Option<i32>,
+    pub destroyed: bool,
+    // Counts down while destroyed, the rocket asks to be respawned when it runs out
+    pub respawn_time: f64,
+    pub invulnerable_time: f64,
+    pub hyperspace_cooldown: f64,
+    // Counts down while the rocket materializes after a jump
+    pub warp_time: f64,
+    // Decided when jumping, takes effect on arrival
+    pub warp_fails: bool,
+    // Seconds left of the timed power-ups
+    pub rapid_fire_time: f64,
+    pub triple_shot_time: f64,
  }
 
 impl Rocket {
-    pub fn update( &mut self, delta_t: f64, state: &GamepadState, objfactory: &GameObjectFactory) -> Vec<Rc<RefCell<dyn GameObject>>> {
+    pub fn update( &mut self, delta_t: f64, state: &GamepadState, game_area: Area, rng: &mut Random, events: &mut EventQueue) {
+        if self.destroyed || self.is_warping() {
+            return;
+        }
+
+        if state.hyperspace && self.hyperspace_cooldown <= 0.0 {
+            self.hyperspace( game_area, rng);
+            return;
+        }
+
         self.thrust( state.thrust);
-        self.rotate( state.rotate);
+        self.rotate( state.rotate, delta_t);
 
         if state.shield {
             self.shield_on( delta_t);
@@ -43,13 +94,11 @@ impl Rocket {
         }
 
         if state.fire {
-            return self.fire_on( delta_t, objfactory)
+            self.fire_on( delta_t, events)
         }
         else {
             self.fire_off( delta_t)
         }
-
-        vec![]
     }
         
     pub fn fire_off( &mut self, delta_t: f64) {
@@ -60,39 +109,58 @@ impl Rocket {
         }
     }
 
-    pub fn fire_on( &mut self, delta_t: f64, objfactory: &GameObjectFactory) -> Vec<Rc<RefCell<dyn GameObject>>> {
+    pub fn fire_on( &mut self, delta_t: f64, events: &mut EventQueue) {
         if self.burst_time < MAX_BURST_TIME {
             self.burst_time += delta_t;
         }
 
-        if (self.last_shot == 0.0 || self.last_shot > 0.2) && (self.burst_time < MAX_BURST_TIME) {
+        if (self.last_shot == 0.0 || self.last_shot > self.shot_interval()) && (self.burst_time < MAX_BURST_TIME) {
             self.last_shot = 0.01;
 
-            let rotvec = Vector::new((self.rotation - FRAC_PI_2).cos(), (self.rotation - FRAC_PI_2).sin()).scale( 25.0);
-            let tempo = Vector::new((self.rotation - FRAC_PI_2).cos(), (self.rotation - FRAC_PI_2).sin()).scale( 250.0).add( &self.speed);
-            let start = self.position.add( &rotvec);
-            let bullet = objfactory.create_bullet( self as *mut Self, start, tempo, self.bullet_color.to_string());
-
-            return vec![ bullet];
+            let angles: &[f64] = if self.triple_shot_time > 0.0 { &[-TRIPLE_SHOT_SPREAD, 0.0, TRIPLE_SHOT_SPREAD] } else { &[0.0] };
+            for angle in angles {
+                let direction = self.rotation + angle - FRAC_PI_2;
+                let rotvec = Vector::new( direction.cos(), direction.sin()).scale( 25.0);
+                let tempo = Vector::new( direction.cos(), direction.sin()).scale( 250.0).add( &self.speed);
+                let start = self.position.add( &rotvec);
+                events.spawn( Spawn::Bullet { player: Some( self.player), team: self.team, position: start, speed: tempo, color: self.color().to_string() });
+            }
         }
         else {
             self.last_shot += delta_t;
         }
+    }   
 
-        vec![]
+    fn shot_interval( &self) -> f64 {
+        if self.rapid_fire_time > 0.0 { RAPID_FIRE_INTERVAL } else { SHOT_INTERVAL }
+    }
 
-    }   
+    // Disappears and materializes somewhere else, hopefully in one piece
+    fn hyperspace( &mut self, game_area: Area, rng: &mut Random) {
+        self.position = Vector::new( rng.random_number_max( game_area.width), rng.random_number_max( game_area.height));
+        self.previous_position = self.position;
+        self.speed = ZERO;
+        self.thrust = 0.0;
+        self.acc = ZERO;
+        self.hyperspace_cooldown = HYPERSPACE_COOLDOWN;
+        self.warp_time = WARP_TIME;
+        self.warp_fails = rng.random_number() < HYPERSPACE_FAILURE_CHANCE;
+    }
+
+    fn is_warping( &self) -> bool {
+        self.warp_time > 0.0
+    }
 
     fn thrust( &mut self, value : f64) {
-        if value >= 0.0 && value <= 1.0 {
+        if (0.0..=1.0).contains( &value) {
             self.thrust = 100.0 * value;
         }
 
         self.update_acc();
     }
 
-    fn rotate( &mut self, value : f64) {
-        self.rotation += value * 0.1;
+    fn rotate( &mut self, value : f64, delta_t: f64) {
+        self.rotation += value * ROTATION_SPEED * delta_t;
 
         self.update_acc();
     }
@@ -117,17 +185,87 @@ impl Rocket {
         self.acc = Vector::new((self.rotation - FRAC_PI_2).cos(), (self.rotation - FRAC_PI_2).sin()).scale(self.thrust); //.add( &GRAVITY);
     }
 
+    fn take_damage( &mut self, amount: i32, events: &mut EventQueue) {
+        self.damage += amount;
+
+        events.emit( GameEvent::Damaged { player: self.player, amount });
+
+        if self.hit_points() <= 0 {
+            self.destroy( events);
+        }
+    }
+
+    fn destroy( &mut self, events: &mut EventQueue) {
+        self.destroyed = true;
+        self.lives = self.lives.map( |lives| lives - 1);
+        self.speed = ZERO;
+        self.thrust = 0.0;
+        self.acc = ZERO;
+        self.shield_on = false;
+        self.shield_time = 0.0;
+
+        if self.lives.is_none_or( |lives| lives > 0) {
+            self.respawn_time = RESPAWN_TIME;
+        }
+
+        events.spawn( Spawn::BigExplosion { position: self.position });
+        events.emit( GameEvent::Destroyed { objtype: GameObjectType::Rocket, position: self.position });
+    }
+
+    // Back in the game at a position the game considers safe, with full hit points
+    pub fn respawn( &mut self, position: Vector) {
+        self.destroyed = false;
+        self.damage = 0;
+        self.position = position;
+        self.previous_position = position;
+        self.rotation = 0.0;
+        self.previous_rotation = 0.0;
+        self.burst_time = 0.0;
+        self.last_shot = 0.0;
+        self.invulnerable_time = INVULNERABLE_TIME;
+    }
+
+    pub fn hit_points( &self) -> i32 {
+        (MAX_HIT_POINTS - self.damage).max( 0)
+    }
+
+    // Share of the burst that can still be fired, 1.0 when fully recharged
+    pub fn burst_left( &self) -> f64 {
+        ((MAX_BURST_TIME - self.burst_time) / MAX_BURST_TIME).clamp( 0.0, 1.0)
+    }
+
+    pub fn is_destroyed( &self) -> bool {
+        self.destroyed
+    }
+
+    // Destroyed for good, the player is out of the game
+    pub fn is_out_of_lives( &self) -> bool {
+        self.destroyed && self.lives.is_some_and( |lives| lives <= 0)
+    }
+
+    // Team color while in a team, otherwise the color the player picked
+    pub fn color( &self) -> &str {
+        match self.team {
+            Some( team) => team_color( team),
+            None => &self.bullet_color,
+        }
+    }
+
+    fn is_invulnerable( &self) -> bool {
+        self.invulnerable_time > 0.0
+    }
+
     fn is_shield_active( &self) -> bool {
         return self.shield_on && (self.shield_time > 0.0) && (self.shield_time < MAX_SHIELD_TIME);
     }
 
-    fn render_score( &self, ctx: &CanvasRenderingContext2d) {
-        ctx.set_font("16px sans-serif");
-        ctx.set_fill_style_str( "black");
+    fn render_score( &self, renderer: &mut dyn Renderer) {
         let score_text = format!("Score: {}", self.score);
-        ctx.fill_text(&score_text, self.score_pos.x, self.score_pos.y).unwrap();
-        let damage_text = format!("Damage: {}", self.damage);
-        ctx.fill_text(&damage_text, self.score_pos.x, self.score_pos.y + 20.0).unwrap();
+        let score_color = if self.team.is_some() { self.color() } else { "black" };
+        renderer.fill_text(&score_text, self.score_pos.x, self.score_pos.y, "16px sans-serif", score_color);
+        let lives = self.lives.map_or( "∞".to_string(), |lives| lives.max( 0).to_string());
+        let damage_text = format!("Lives: {}  HP: {}", lives, self.hit_points());
+        renderer.fill_text(&damage_text, self.score_pos.x, self.score_pos.y + 20.0, "16px sans-serif", "black");
 
         // Draw burst time remaining bar under score
         let max_width = 100.0;
@@ -136,8 +274,18 @@ impl Rocket {
         let bar_x = self.score_pos.x;
         let bar_y = self.score_pos.y + 40.0;
 
-        ctx.set_fill_style_str( "red");
-        ctx.fill_rect(bar_x, bar_y, bar_width, bar_height);
+        renderer.fill_rect(bar_x, bar_y, bar_width, bar_height, "red");
+
+        // Icons of the timed power-ups, with the seconds left
+        let timed = [(PowerUpKind::RapidFire, self.rapid_fire_time), (PowerUpKind::TripleShot, self.triple_shot_time)];
+        let mut icon_x = self.score_pos.x + 8.0;
+        for (kind, time) in timed.iter().filter( |(_, time)| *time > 0.0) {
+            let (letter, color) = kind.icon();
+            renderer.fill_circle( icon_x, bar_y + 18.0, 8.0, color);
+            renderer.fill_text( letter, icon_x - 4.0, bar_y + 18.0, "10px sans-serif", "white");
+            renderer.fill_text( &format!( "{:.0}", time.ceil()), icon_x + 11.0, bar_y + 18.0, "10px sans-serif", "black");
+            icon_x += 40.0;
+        }
     }
 
 }
@@ -155,6 +303,10 @@ impl GameObject for Rocket {
         return GameObjectType::Rocket;
     }
 
+    fn snapshot( &self) -> ObjectSnapshot {
+        ObjectSnapshot::Rocket( self.clone())
+    }
+
     fn current_position(&self) -> Vector {
         self.position
     }
@@ -167,7 +319,41 @@ impl GameObject for Rocket {
         return false;
     }
 
-    fn move_t(&mut self, delta_t: f64, game_area: Area) {
+    fn move_t(&mut self, delta_t: f64, game_area: Area, events: &mut EventQueue) {
+        if self.destroyed {
+            if self.respawn_time > 0.0 {
+                self.respawn_time -= delta_t;
+
+                if self.respawn_time <= 0.0 {
+                    events.emit( GameEvent::RespawnRequested { player: self.player });
+                }
+            }
+
+            return;
+        }
+
+        if self.invulnerable_time > 0.0 {
+            self.invulnerable_time -= delta_t;
+        }
+
+        if self.hyperspace_cooldown > 0.0 {
+            self.hyperspace_cooldown -= delta_t;
+        }
+
+        self.rapid_fire_time = (self.rapid_fire_time - delta_t).max( 0.0);
+        self.triple_shot_time = (self.triple_shot_time - delta_t).max( 0.0);
+
+        if self.is_warping() {
+            self.warp_time -= delta_t;
+
+            if !self.is_warping() && self.warp_fails {
+                self.warp_fails = false;
+                self.destroy( events);
+            }
+
+            return;
+        }
+
         self.speed = self.speed.add( &self.acc.scale(delta_t));
         self.position = self.position.add( &self.speed.scale(delta_t));
 
@@ -199,63 +385,137 @@ impl GameObject for Rocket {
         }
     }
 
-    fn render(&self, ctx: &CanvasRenderingContext2d) {
-        
+    fn velocity( &self) -> Vector {
+        self.speed
+    }
+
+    fn push( &mut self, velocity_change: Vector, offset: Vector) {
+        self.speed = self.speed.add( &velocity_change);
+        self.position = self.position.add( &offset);
+    }
+
+    fn store_previous_state( &mut self) {
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
+    }
+
+    fn previous_position( &self) -> Vector {
+        self.previous_position
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, interpolation: f64) {
+        // Blinks while invulnerable
+        let hidden = self.is_invulnerable() && (self.invulnerable_time / BLINK_INTERVAL) as i32 % 2 == 1;
+        if self.destroyed || hidden {
+            self.render_score(renderer);
+            return;
+        }
+
         let sprite = if self.thrust > 0.0 { &self.sprite_on } else { &self.sprite_off };
+        let position = interpolate( self.previous_position, self.position, interpolation);
+        let rotation = self.previous_rotation + (self.rotation - self.previous_rotation) * interpolation;
+
+        renderer.save();
+        renderer.translate(position.x, position.y);
+        renderer.rotate( rotation);
+
+        // Grows out of a collapsing ring while materializing
+        if self.is_warping() {
+            let progress = 1.0 - self.warp_time / WARP_TIME;
+            renderer.stroke_circle( 0.0, 0.0, self.radius() * (3.0 - 2.0 * progress), 2.0, "rgba(150, 0, 255, 0.7)");
+            renderer.scale( progress, progress);
+        }
+
+        renderer.draw_sprite( sprite, 0.0, 0.0);
 
-        ctx.save();
-        ctx.translate(self.position.x, self.position.y).unwrap();
-        ctx.rotate( self.rotation).unwrap();
-        ctx.draw_image_with_html_image_element_and_dw_and_dh(
-            &sprite,
-            - (sprite.width() as f64 / 2.0),
-            - (sprite.height() as f64 / 2.0),
-            sprite.width() as f64,
-            sprite.height() as f64,
-        ).unwrap();
+        // Ring in the team color, so teammates can be told apart from opponents
+        if self.team.is_some() {
+            renderer.stroke_circle( 0.0, 0.0, self.radius() + 4.0, 2.0, self.color());
+        }
 
         // Draw a shield circle
         if self.is_shield_active() && self.shield_time > 0.0 {
-            ctx.begin_path();
-            ctx.arc(0.0, 0.0, self.radius() + 10.0, 0.0, PI * 2.0).unwrap();
-            ctx.set_stroke_style_str( "rgba(0, 200, 255, 0.5)");
-            ctx.set_line_width((MAX_SHIELD_TIME - (self.shield_time)) * (MAX_SHIELD_STROKE_WIDTH / MAX_SHIELD_TIME));
-            ctx.stroke();
+            let line_width = (MAX_SHIELD_TIME - (self.shield_time)) * (MAX_SHIELD_STROKE_WIDTH / MAX_SHIELD_TIME);
+            renderer.stroke_circle(0.0, 0.0, self.radius() + 10.0, line_width, "rgba(0, 200, 255, 0.5)");
         }
 
-        ctx.restore();
+        renderer.restore();
 
-        self.render_score(ctx);
+        self.render_score(renderer);
     }
 
     fn radius( &self) -> f64 {
         20.0
     }
 
-    fn collision_with(&mut self, objtype: GameObjectType, objfactory: &GameObjectFactory) -> Vec<Rc<RefCell<dyn GameObject>>> {
+    fn collision_layer( &self) -> u32 {
+        if self.destroyed || self.is_invulnerable() || self.is_warping() {
+            return LAYER_NONE;
+        }
+
+        LAYER_ROCKET
+    }
+
+    fn collision_mask( &self) -> u32 {
+        if self.destroyed || self.is_warping() {
+            return LAYER_NONE;
+        }
+
+        // Nothing can hurt it, but it can still pick up power-ups
+        if self.is_invulnerable() {
+            return LAYER_POWER_UP;
+        }
+
+        LAYER_ASTEROID | LAYER_BULLET | LAYER_SAUCER_BULLET | LAYER_ROCKET | LAYER_POWER_UP | LAYER_SAUCER
+    }
+
+    fn team( &self) -> Option<usize> {
+        self.team
+    }
+
+    fn hull( &self) -> Option<Vec<Vector>> {
+        Some( self.hull.rotated( self.rotation))
+    }
+
+    fn collect( &mut self, power_up: PowerUpKind, _events: &mut EventQueue) {
+        match power_up {
+            PowerUpKind::RapidFire => self.rapid_fire_time = POWER_UP_TIME,
+            PowerUpKind::TripleShot => self.triple_shot_time = POWER_UP_TIME,
+            PowerUpKind::ShieldRecharge => self.shield_time = 0.0,
+            PowerUpKind::Repair => self.damage = 0,
+            PowerUpKind::ExtraLife => self.lives = self.lives.map( |lives| lives + 1),
+        }
+    }
+
+    fn collision_with(&mut self, objtype: GameObjectType, _rng: &mut Random, events: &mut EventQueue) {
         match objtype {
             GameObjectType::Asteroid => {
                 if !self.is_shield_active() {
-                    self.damage += 100;
+                    self.take_damage( 100, events);
                 }
-                return vec![objfactory.create_explosion(self.position)];
+                events.spawn( Spawn::Explosion { position: self.position });
             }
             GameObjectType::Bullet => {
                 if !self.is_shield_active() {
-                    self.damage += 50;
+                    self.take_damage( 50, events);
                 } else {
                     self.shield_time += 0.01;
                 }
             }
+            GameObjectType::Saucer => {
+                if !self.is_shield_active() {
+                    self.take_damage( 300, events);
+                }
+                events.spawn( Spawn::Explosion { position: self.position });
+            }
             GameObjectType::Rocket => {
                 if !self.is_shield_active() {
-                    self.damage += 500;
+                    self.take_damage( 500, events);
                 } else {
                     self.shield_time += 0.05;
                 }
             }
             _ => {}
         }
-        vec![]
     }
 }
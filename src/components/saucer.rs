@@ -0,0 +1,158 @@
+use serde::{Serialize, Deserialize};
+use crate::utils::{Vector, Random};
+use crate::engine::collision::{LAYER_BULLET, LAYER_ROCKET, LAYER_SAUCER};
+use crate::engine::{GameObject, ObjectSnapshot, GameObjectType, Area, EventQueue, GameEvent, Spawn, Renderer, interpolate};
+use std::any::Any;
+
+// Seconds between two changes of the vertical direction
+const ZIGZAG_INTERVAL: f64 = 1.2;
+pub const ZIGZAG_SPEED: f64 = 60.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaucerSize {
+    Large,
+    Small,
+}
+
+impl SaucerSize {
+    pub fn radius( &self) -> f64 {
+        match self {
+            SaucerSize::Large => 24.0,
+            SaucerSize::Small => 14.0,
+        }
+    }
+
+    pub fn speed( &self) -> f64 {
+        match self {
+            SaucerSize::Large => 80.0,
+            SaucerSize::Small => 130.0,
+        }
+    }
+
+    // Seconds between two shots
+    pub fn fire_interval( &self) -> f64 {
+        match self {
+            SaucerSize::Large => 1.5,
+            SaucerSize::Small => 1.0,
+        }
+    }
+
+    // Largest deviation in radians from a perfect shot at the nearest rocket
+    pub fn aim_error( &self) -> f64 {
+        match self {
+            SaucerSize::Large => 0.6,
+            SaucerSize::Small => 0.1,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Saucer {
+    pub size: SaucerSize,
+    pub expired: bool,
+    pub position: Vector,
+    pub previous_position: Vector,
+    pub speed: Vector,
+    pub zigzag_time: f64,
+    pub fire_time: f64,
+ }
+
+impl GameObject for Saucer {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn get_type( &self) -> GameObjectType {
+        return GameObjectType::Saucer;
+    }
+
+    fn snapshot( &self) -> ObjectSnapshot {
+        ObjectSnapshot::Saucer( self.clone())
+    }
+
+    fn current_position(&self) -> Vector {
+        self.position
+    }
+
+    fn expire( &mut self) {
+        self.expired = true;
+    }
+
+    fn is_expired( &self) -> bool {
+        return self.expired;
+    }
+
+    // Crosses the screen once from side to side, wrapping around at the top and bottom only
+    fn move_t(&mut self, delta_t: f64, game_area: Area, events: &mut EventQueue) {
+        self.position = self.position.add( &self.speed.scale( delta_t));
+
+        let radius = self.radius();
+        if self.position.x < -radius || self.position.x > game_area.width + radius {
+            self.expire();
+            return;
+        }
+
+        if self.position.y > game_area.height {
+            self.position.y = 0.0;
+        }
+
+        if self.position.y < 0.0 {
+            self.position.y = game_area.height;
+        }
+
+        self.zigzag_time += delta_t;
+        if self.zigzag_time > ZIGZAG_INTERVAL {
+            self.zigzag_time = 0.0;
+            self.speed.y = -self.speed.y;
+        }
+
+        self.fire_time += delta_t;
+        if self.fire_time > self.size.fire_interval() {
+            self.fire_time = 0.0;
+
+            // Only the game knows where the rockets are
+            events.spawn( Spawn::SaucerBullet { position: self.position, clearance: radius, aim_error: self.size.aim_error() });
+        }
+    }
+
+    fn store_previous_state( &mut self) {
+        self.previous_position = self.position;
+    }
+
+    fn previous_position( &self) -> Vector {
+        self.previous_position
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, interpolation: f64) {
+        let position = interpolate( self.previous_position, self.position, interpolation);
+        let radius = self.radius();
+
+        renderer.fill_circle( position.x, position.y - radius * 0.3, radius * 0.5, "gray");
+        renderer.fill_rect( position.x - radius, position.y - radius * 0.2, 2.0 * radius, radius * 0.5, "dimgray");
+    }
+
+    fn radius( &self) -> f64 {
+        self.size.radius()
+    }
+
+    fn collision_layer( &self) -> u32 {
+        LAYER_SAUCER
+    }
+
+    fn collision_mask( &self) -> u32 {
+        LAYER_ROCKET | LAYER_BULLET
+    }
+
+    fn collision_with(&mut self, objtype: GameObjectType, _rng: &mut Random, events: &mut EventQueue) {
+        if objtype == GameObjectType::Bullet || objtype == GameObjectType::Rocket {
+            self.expire();
+
+            events.emit( GameEvent::Destroyed { objtype: GameObjectType::Saucer, position: self.position });
+            events.spawn( Spawn::Explosion { position: self.position });
+        }
+    }
+}
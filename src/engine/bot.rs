@@ -0,0 +1,180 @@
+use std::f64::consts::{FRAC_PI_2, PI};
+use serde::{Serialize, Deserialize};
+use wasm_bindgen::prelude::*;
+use crate::utils::Vector;
+use crate::engine::{Area, Entities, EntityId, GameObject, GameObjectType, GamepadState};
+use crate::engine::collision::LAYER_NONE;
+use crate::components::{Bullet, Rocket};
+
+// Targets further away than this are approached before shooting at them
+const APPROACH_DISTANCE: f64 = 300.0;
+// Bots do not thrust any faster than this
+const MAX_BOT_SPEED: f64 = 150.0;
+// Bots stop firing before the burst is empty, so they are never stuck waiting for it to recharge
+const MIN_BURST_LEFT: f64 = 0.2;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotDifficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl BotDifficulty {
+    // Seconds between two decisions, the input is held in between
+    fn reaction_time( &self) -> f64 {
+        match self {
+            BotDifficulty::Easy => 0.3,
+            BotDifficulty::Medium => 0.12,
+            BotDifficulty::Hard => 0.0,
+        }
+    }
+
+    // Fires when the rocket points at the target within this many radians
+    fn aim_tolerance( &self) -> f64 {
+        match self {
+            BotDifficulty::Easy => 0.35,
+            BotDifficulty::Medium => 0.15,
+            BotDifficulty::Hard => 0.06,
+        }
+    }
+
+    // Gap to the nearest threat at which the bot starts dodging
+    fn danger_distance( &self) -> f64 {
+        match self {
+            BotDifficulty::Easy => 40.0,
+            BotDifficulty::Medium => 70.0,
+            BotDifficulty::Hard => 100.0,
+        }
+    }
+
+    fn uses_shield( &self) -> bool {
+        *self != BotDifficulty::Easy
+    }
+}
+
+// Computer player that looks at the world and decides which buttons to press, like a human with a gamepad
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bot {
+    pub player: usize,
+    pub difficulty: BotDifficulty,
+    think_time: f64,
+    state: GamepadState,
+}
+
+impl Bot {
+    pub fn new( player: usize, difficulty: BotDifficulty) -> Self {
+        let mut state = GamepadState::new();
+        state.rocket_index = player;
+
+        Bot { player, difficulty, think_time: 0.0, state }
+    }
+
+    // Other rockets are only shot at when hunt_rockets is set, i.e. when the game mode awards points for them
+    pub fn control( &mut self, rocket: EntityId, shapes: &Entities, game_area: Area, hunt_rockets: bool, delta_t: f64) -> GamepadState {
+        self.think_time -= delta_t;
+
+        if self.think_time <= 0.0 {
+            self.think_time = self.difficulty.reaction_time();
+            self.state = self.think( rocket, shapes, game_area, hunt_rockets);
+        }
+
+        return self.state;
+    }
+
+    fn think( &self, id: EntityId, shapes: &Entities, game_area: Area, hunt_rockets: bool) -> GamepadState {
+        let mut state = GamepadState::new();
+        state.rocket_index = self.player;
+
+        let Some( object) = shapes.get( id) else {
+            return state;
+        };
+        let object = object.borrow();
+        let Some( rocket) = object.as_any().downcast_ref::<Rocket>() else {
+            return state;
+        };
+
+        if rocket.is_destroyed() {
+            return state;
+        }
+
+        // Nearest thing that can hurt the rocket and nearest thing worth shooting, as vector from the rocket and gap
+        let mut threat: Option<(Vector, f64)> = None;
+        let mut target: Option<(Vector, f64)> = None;
+        let mut opponent: Option<(Vector, f64)> = None;
+
+        for (other_id, other) in shapes.iter() {
+            if other_id == id {
+                continue;
+            }
+
+            let other = other.borrow();
+            if other.collision_layer() == LAYER_NONE {
+                continue;
+            }
+
+            let delta = game_area.delta( &other.current_position(), &rocket.position);
+            let gap = delta.length() - other.radius() - rocket.radius();
+            let closer = |current: &Option<(Vector, f64)>| current.is_none_or( |(_, nearest)| gap < nearest);
+            let teammate = rocket.team.is_some() && other.team() == rocket.team;
+
+            match other.get_type() {
+                GameObjectType::Asteroid | GameObjectType::Saucer => {
+                    if closer( &threat) {
+                        threat = Some( (delta, gap));
+                    }
+                    if closer( &target) {
+                        target = Some( (delta, gap));
+                    }
+                }
+                GameObjectType::Bullet => {
+                    let hostile = !teammate && other.as_any().downcast_ref::<Bullet>().is_some_and( |bullet| bullet.player != Some( self.player));
+                    if hostile && closer( &threat) {
+                        threat = Some( (delta, gap));
+                    }
+                }
+                GameObjectType::Rocket if hunt_rockets && !teammate && closer( &opponent) => {
+                    opponent = Some( (delta, gap));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some( (delta, gap)) = threat.filter( |(_, gap)| *gap < self.difficulty.danger_distance()) {
+            state.shield = self.difficulty.uses_shield() && gap < self.difficulty.danger_distance() / 2.0;
+
+            // Turn away from the threat and get out of its way
+            let away = delta.scale( -1.0);
+            state.rotate = steer( rocket, &away);
+            state.thrust = if facing_error( rocket, &away).abs() < FRAC_PI_2 { 1.0 } else { 0.0 };
+
+            return state;
+        }
+
+        // Asteroids and saucers first, the other rockets when there is nothing else
+        let Some( (delta, gap)) = target.or( opponent) else {
+            return state;
+        };
+
+        let error = facing_error( rocket, &delta);
+        state.rotate = steer( rocket, &delta);
+        state.fire = error.abs() < self.difficulty.aim_tolerance() && rocket.burst_left() > MIN_BURST_LEFT;
+        state.thrust = if gap > APPROACH_DISTANCE && error.abs() < 0.5 && rocket.speed.length() < MAX_BOT_SPEED { 0.6 } else { 0.0 };
+
+        return state;
+    }
+}
+
+// Angle in radians between where the rocket points and the direction, in -PI..PI
+fn facing_error( rocket: &Rocket, direction: &Vector) -> f64 {
+    let facing = rocket.rotation - FRAC_PI_2;
+    let desired = direction.y.atan2( direction.x);
+
+    (desired - facing + PI).rem_euclid( 2.0 * PI) - PI
+}
+
+// Stick deflection that turns the rocket towards the direction
+fn steer( rocket: &Rocket, direction: &Vector) -> f64 {
+    (facing_error( rocket, direction) * 3.0).clamp( -1.0, 1.0)
+}
@@ -0,0 +1,72 @@
+use crate::utils::Vector;
+use crate::engine::Area;
+
+// Uniform grid over the game area. Objects are registered in every cell their bounding box touches,
+// cells wrap around at the edges just like the objects do, so only objects sharing a cell need
+// the exact (narrow phase) test.
+pub struct SpatialHash {
+    cell_width: f64,
+    cell_height: f64,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl SpatialHash {
+    // Cells are at least cell_size wide and high, and stretched so they evenly divide the area
+    pub fn new( area: Area, cell_size: f64) -> Self {
+        let columns = ((area.width / cell_size).floor() as usize).max( 1);
+        let rows = ((area.height / cell_size).floor() as usize).max( 1);
+
+        SpatialHash {
+            cell_width: area.width / columns as f64,
+            cell_height: area.height / rows as f64,
+            columns,
+            rows,
+            cells: vec![vec![]; columns * rows],
+        }
+    }
+
+    pub fn clear( &mut self) {
+        self.cells.iter_mut().for_each( |cell| cell.clear());
+    }
+
+    pub fn insert( &mut self, index: usize, position: Vector, radius: f64) {
+        let (first_column, last_column) = Self::cell_range( position.x - radius, position.x + radius, self.cell_width, self.columns);
+        let (first_row, last_row) = Self::cell_range( position.y - radius, position.y + radius, self.cell_height, self.rows);
+
+        for row in first_row..=last_row {
+            for column in first_column..=last_column {
+                let cell = row.rem_euclid( self.rows as i64) as usize * self.columns + column.rem_euclid( self.columns as i64) as usize;
+                self.cells[cell].push( index);
+            }
+        }
+    }
+
+    // Unique pairs (i < j) of objects sharing at least one cell, sorted so the result does not depend on the grid layout
+    pub fn candidate_pairs( &self) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+
+        for objects in self.cells.iter() {
+            for i in 0..objects.len() {
+                for j in (i + 1)..objects.len() {
+                    let (a, b) = (objects[i], objects[j]);
+                    pairs.push( if a < b { (a, b) } else { (b, a) });
+                }
+            }
+        }
+
+        pairs.sort_unstable();
+        pairs.dedup();
+
+        return pairs;
+    }
+
+    fn cell_range( min: f64, max: f64, cell_size: f64, count: usize) -> (i64, i64) {
+        let first = (min / cell_size).floor() as i64;
+        let last = (max / cell_size).floor() as i64;
+
+        // Never cover a cell more than once, even when the object is larger than the whole area
+        (first, last.min( first + count as i64 - 1))
+    }
+}
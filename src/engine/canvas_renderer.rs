@@ -0,0 +1,99 @@
+use web_sys::{CanvasRenderingContext2d, HtmlImageElement};
+use std::f64::consts::PI;
+use crate::engine::renderer::{Renderer, Sprite};
+
+pub struct CanvasRenderer {
+    ctx: CanvasRenderingContext2d,
+    images: Vec<HtmlImageElement>,
+}
+
+impl CanvasRenderer {
+    pub fn new( ctx: CanvasRenderingContext2d) -> Self {
+        CanvasRenderer {
+            ctx,
+            images: vec![],
+        }
+    }
+
+    pub fn add_sprite( &mut self, image: HtmlImageElement) -> Sprite {
+        let sprite = Sprite {
+            id: self.images.len(),
+            width: image.width() as f64,
+            height: image.height() as f64,
+        };
+
+        self.images.push( image);
+
+        return sprite;
+    }
+}
+
+impl Renderer for CanvasRenderer {
+    fn clear( &mut self, width: f64, height: f64) {
+        self.ctx.clear_rect( 0.0, 0.0, width, height);
+    }
+
+    fn save( &mut self) {
+        self.ctx.save();
+    }
+
+    fn restore( &mut self) {
+        self.ctx.restore();
+    }
+
+    fn translate( &mut self, x: f64, y: f64) {
+        self.ctx.translate( x, y).unwrap();
+    }
+
+    fn rotate( &mut self, angle: f64) {
+        self.ctx.rotate( angle).unwrap();
+    }
+
+    fn scale( &mut self, x: f64, y: f64) {
+        let _ = self.ctx.scale( x, y);
+    }
+
+    fn set_alpha( &mut self, alpha: f64) {
+        self.ctx.set_global_alpha( alpha);
+    }
+
+    fn draw_sprite( &mut self, sprite: &Sprite, x: f64, y: f64) {
+        if let Some( image) = self.images.get( sprite.id) {
+            self.ctx.draw_image_with_html_image_element_and_dw_and_dh(
+                image,
+                x - (sprite.width / 2.0),
+                y - (sprite.height / 2.0),
+                sprite.width,
+                sprite.height,
+            ).unwrap();
+        }
+    }
+
+    fn fill_circle( &mut self, x: f64, y: f64, radius: f64, color: &str) {
+        self.ctx.begin_path();
+        self.ctx.arc( x, y, radius, 0.0, PI * 2.0).unwrap();
+        self.ctx.set_fill_style_str( color);
+        self.ctx.fill();
+    }
+
+    fn stroke_circle( &mut self, x: f64, y: f64, radius: f64, line_width: f64, color: &str) {
+        self.ctx.begin_path();
+        self.ctx.arc( x, y, radius, 0.0, PI * 2.0).unwrap();
+        self.ctx.set_stroke_style_str( color);
+        self.ctx.set_line_width( line_width);
+        self.ctx.stroke();
+    }
+
+    fn fill_rect( &mut self, x: f64, y: f64, width: f64, height: f64, color: &str) {
+        self.ctx.set_fill_style_str( color);
+        self.ctx.fill_rect( x, y, width, height);
+    }
+
+    fn fill_text( &mut self, text: &str, x: f64, y: f64, font: &str, color: &str) {
+        self.ctx.set_font( font);
+        self.ctx.set_fill_style_str( color);
+        self.ctx.set_text_align( "left");
+        self.ctx.set_text_baseline( "middle");
+        self.ctx.fill_text( text, x, y).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use crate::engine::GameObjectType;
+
+// Rules the game ships with, designers can load their own with Game::load_collisions
+const DEFAULT_COLLISIONS: &str = include_str!( "../../assets/collisions.json");
+
+// Collision layers, an object only collides with objects on a layer in its mask
+pub const LAYER_NONE: u32 = 0;
+pub const LAYER_ASTEROID: u32 = 1 << 0;
+pub const LAYER_ROCKET: u32 = 1 << 1;
+pub const LAYER_BULLET: u32 = 1 << 2;
+pub const LAYER_POWER_UP: u32 = 1 << 3;
+pub const LAYER_SAUCER: u32 = 1 << 4;
+// Bullets of saucers, which saucers do not collide with
+pub const LAYER_SAUCER_BULLET: u32 = 1 << 5;
+pub const LAYER_ALL: u32 = u32::MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollisionResponse {
+    // The object gets hit, what that means is up to its collision_with
+    Damage,
+    // The object is pushed away from the other one
+    Bounce,
+    // Both objects exchange momentum according to their mass, only when both sides of the pair are Elastic
+    Elastic,
+    // The object takes what the other one offers, see GameObject::take_power_up
+    Collect,
+    PassThrough,
+}
+
+// Response of an object touching another one, as written in assets/collisions.json
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CollisionRule {
+    pub object: GameObjectType,
+    pub other: GameObjectType,
+    pub response: CollisionResponse,
+    // Same response for the other object
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub both: bool,
+}
+
+// Which response an object of one type gets when it touches an object of another type.
+// Pairs without a rule pass through each other.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<CollisionRule>", into = "Vec<CollisionRule>")]
+pub struct CollisionMatrix {
+    rules: BTreeMap<(GameObjectType, GameObjectType), CollisionResponse>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CollisionsError {
+    Invalid( String),
+}
+
+impl fmt::Display for CollisionsError {
+    fn fmt( &self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollisionsError::Invalid( reason) => write!( f, "invalid collisions: {}", reason),
+        }
+    }
+}
+
+impl CollisionMatrix {
+    pub fn new() -> Self {
+        CollisionMatrix { rules: BTreeMap::new() }
+    }
+
+    pub fn from_json( json: &str) -> Result<CollisionMatrix, CollisionsError> {
+        serde_json::from_str( json).map_err( |e| CollisionsError::Invalid( e.to_string()))
+    }
+
+    pub fn to_json( &self) -> String {
+        serde_json::to_string_pretty( self).expect( "collision matrix is always serializable")
+    }
+
+    // Response of an object of type objtype touching an object of type other
+    pub fn set( &mut self, objtype: GameObjectType, other: GameObjectType, response: CollisionResponse) -> &mut Self {
+        self.rules.insert( (objtype, other), response);
+        self
+    }
+
+    // Same response for both objects
+    pub fn set_both( &mut self, a: GameObjectType, b: GameObjectType, response: CollisionResponse) -> &mut Self {
+        self.set( a, b, response).set( b, a, response)
+    }
+
+    pub fn response( &self, objtype: GameObjectType, other: GameObjectType) -> CollisionResponse {
+        self.rules.get( &(objtype, other)).copied().unwrap_or( CollisionResponse::PassThrough)
+    }
+
+    // Whether the pair needs the exact collision test at all
+    pub fn interacts( &self, a: GameObjectType, b: GameObjectType) -> bool {
+        self.response( a, b) != CollisionResponse::PassThrough || self.response( b, a) != CollisionResponse::PassThrough
+    }
+}
+
+impl From<Vec<CollisionRule>> for CollisionMatrix {
+    fn from( rules: Vec<CollisionRule>) -> Self {
+        let mut matrix = CollisionMatrix::new();
+
+        for rule in rules {
+            if rule.both {
+                matrix.set_both( rule.object, rule.other, rule.response);
+            }
+            else {
+                matrix.set( rule.object, rule.other, rule.response);
+            }
+        }
+
+        matrix
+    }
+}
+
+// One rule per direction, sorted, so equal matrices always serialize the same
+impl From<CollisionMatrix> for Vec<CollisionRule> {
+    fn from( matrix: CollisionMatrix) -> Self {
+        matrix.rules.into_iter()
+            .map( |((object, other), response)| CollisionRule { object, other, response, both: false })
+            .collect()
+    }
+}
+
+impl Default for CollisionMatrix {
+    fn default() -> Self {
+        CollisionMatrix::from_json( DEFAULT_COLLISIONS).expect( "built-in collisions are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_rules() {
+        let matrix = CollisionMatrix::default();
+
+        assert_eq!( matrix.response( GameObjectType::Asteroid, GameObjectType::Asteroid), CollisionResponse::Elastic);
+        assert_eq!( matrix.response( GameObjectType::Bullet, GameObjectType::Asteroid), CollisionResponse::Damage);
+        assert_eq!( matrix.response( GameObjectType::Rocket, GameObjectType::Rocket), CollisionResponse::Damage);
+        assert_eq!( matrix.response( GameObjectType::Rocket, GameObjectType::PowerUp), CollisionResponse::Collect);
+        assert_eq!( matrix.response( GameObjectType::PowerUp, GameObjectType::Rocket), CollisionResponse::PassThrough);
+        assert!( !matrix.interacts( GameObjectType::Bullet, GameObjectType::Bullet));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = r#"[{ "object": "Rocket", "other": "Rocket", "response": "Bounce" },
+                       { "object": "Rocket", "other": "Asteroid", "response": "Bounce", "both": true }]"#;
+        let matrix = CollisionMatrix::from_json( json).unwrap();
+
+        assert_eq!( matrix.response( GameObjectType::Asteroid, GameObjectType::Rocket), CollisionResponse::Bounce);
+        assert_eq!( CollisionMatrix::from_json( &matrix.to_json()), Ok( matrix));
+        assert!( CollisionMatrix::from_json( r#"[{ "object": "Rocket", "other": "Moon", "response": "Bounce" }]"#).is_err());
+    }
+}
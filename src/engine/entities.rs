@@ -0,0 +1,306 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use crate::engine::{GameObject, ObjectSnapshot, SnapshotError};
+
+// Handle to an object in the game world. A handle to a removed object stays invalid,
+// even when its slot is reused by another object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot {
+    generation: u32,
+    object: Option<Rc<RefCell<dyn GameObject>>>,
+}
+
+// Slots including their generation, so handles stay valid across save and restore
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EntitiesSnapshot {
+    slots: Vec<(u32, Option<ObjectSnapshot>)>,
+    free: Vec<u32>,
+}
+
+// Slots that changed between two snapshots, see EntitiesSnapshot::delta
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EntitiesDelta {
+    slot_count: usize,
+    changed: Vec<SlotChange>,
+    free: Vec<u32>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum SlotChange {
+    // Another object or none at all
+    Replaced { index: u32, generation: u32, object: Option<Box<ObjectSnapshot>> },
+    // Same object, only the fields that changed
+    Patched { index: u32, patch: Value },
+}
+
+impl EntitiesSnapshot {
+    pub fn objects( &self) -> impl Iterator<Item = &ObjectSnapshot> {
+        self.slots.iter().filter_map( |(_, object)| object.as_ref())
+    }
+
+    pub fn delta( &self, base: &EntitiesSnapshot) -> EntitiesDelta {
+        let changed = self.slots.iter().enumerate()
+            .filter_map( |(index, (generation, object))| {
+                let index = index as u32;
+
+                match (base.slots.get( index as usize), object) {
+                    (Some( (base_generation, Some( base_object))), Some( object)) if base_generation == generation => {
+                        let patch = diff( &to_value( base_object), &to_value( object));
+                        let unchanged = patch.as_object().is_some_and( |fields| fields.is_empty());
+
+                        (!unchanged).then_some( SlotChange::Patched { index, patch })
+                    }
+                    (Some( (base_generation, None)), None) if base_generation == generation => None,
+                    _ => Some( SlotChange::Replaced { index, generation: *generation, object: object.clone().map( Box::new) }),
+                }
+            })
+            .collect();
+
+        EntitiesDelta {
+            slot_count: self.slots.len(),
+            changed,
+            free: self.free.clone(),
+        }
+    }
+}
+
+impl EntitiesDelta {
+    pub fn apply( &self, base: &EntitiesSnapshot) -> Result<EntitiesSnapshot, SnapshotError> {
+        let mut slots = base.slots.clone();
+        slots.resize( self.slot_count, (0, None));
+
+        for change in &self.changed {
+            match change {
+                SlotChange::Replaced { index, generation, object } => {
+                    let slot = slots.get_mut( *index as usize).ok_or_else( || SnapshotError::Invalid( format!( "no slot {}", index)))?;
+                    *slot = (*generation, object.clone().map( |object| *object));
+                }
+                SlotChange::Patched { index, patch } => {
+                    let Some( (_, Some( object))) = slots.get_mut( *index as usize) else {
+                        return Err( SnapshotError::Invalid( format!( "no object to patch in slot {}", index)));
+                    };
+
+                    let mut value = to_value( object);
+                    merge( &mut value, patch);
+                    *object = serde_json::from_value( value).map_err( |e| SnapshotError::Invalid( e.to_string()))?;
+                }
+            }
+        }
+
+        Ok( EntitiesSnapshot { slots, free: self.free.clone() })
+    }
+}
+
+fn to_value( object: &ObjectSnapshot) -> Value {
+    serde_json::to_value( object).expect( "object snapshot is always serializable")
+}
+
+// Fields of new that differ from base. Objects with different fields, e.g. another enum variant, are replaced as a whole.
+fn diff( base: &Value, new: &Value) -> Value {
+    match (base, new) {
+        (Value::Object( base), Value::Object( new)) if base.len() == new.len() && new.keys().all( |key| base.contains_key( key)) => {
+            Value::Object( new.iter()
+                .filter( |(key, value)| !identical( &base[key.as_str()], value))
+                .map( |(key, value)| (key.clone(), diff( &base[key.as_str()], value)))
+                .collect())
+        }
+        _ => new.clone(),
+    }
+}
+
+// Like ==, but tells 0.0 and -0.0 apart, so the patched object is exactly the same
+fn identical( a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number( a), Value::Number( b)) => a.to_string() == b.to_string(),
+        (Value::Array( a), Value::Array( b)) => a.len() == b.len() && a.iter().zip( b).all( |(a, b)| identical( a, b)),
+        (Value::Object( a), Value::Object( b)) => a.len() == b.len() && a.iter().all( |(key, value)| b.get( key).is_some_and( |other| identical( value, other))),
+        (a, b) => a == b,
+    }
+}
+
+fn merge( base: &mut Value, patch: &Value) {
+    match (base, patch) {
+        (Value::Object( base), Value::Object( patch)) => {
+            for (key, value) in patch {
+                match base.get_mut( key) {
+                    Some( field) => merge( field, value),
+                    None => { base.insert( key.clone(), value.clone()); }
+                }
+            }
+        }
+        (base, patch) => *base = patch.clone(),
+    }
+}
+
+// Generational arena owning all objects of the game world
+#[derive(Default)]
+pub struct Entities {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Entities {
+            slots: vec![],
+            free: vec![],
+        }
+    }
+
+    pub fn insert( &mut self, object: Rc<RefCell<dyn GameObject>>) -> EntityId {
+        if let Some( index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.object = Some( object);
+
+            return EntityId { index, generation: slot.generation };
+        }
+
+        self.slots.push( Slot { generation: 0, object: Some( object) });
+
+        return EntityId { index: (self.slots.len() - 1) as u32, generation: 0 };
+    }
+
+    pub fn remove( &mut self, id: EntityId) -> Option<Rc<RefCell<dyn GameObject>>> {
+        let slot = self.slots.get_mut( id.index as usize)?;
+
+        if slot.generation != id.generation || slot.object.is_none() {
+            return None;
+        }
+
+        slot.generation = slot.generation.wrapping_add( 1);
+        self.free.push( id.index);
+
+        return slot.object.take();
+    }
+
+    pub fn get( &self, id: EntityId) -> Option<&Rc<RefCell<dyn GameObject>>> {
+        self.slots.get( id.index as usize)
+            .filter( |slot| slot.generation == id.generation)
+            .and_then( |slot| slot.object.as_ref())
+    }
+
+    pub fn contains( &self, id: EntityId) -> bool {
+        self.get( id).is_some()
+    }
+
+    // Runs f on the object if it still exists, has type T and is not borrowed elsewhere right now
+    pub fn with_mut<T: GameObject, R>( &self, id: EntityId, f: impl FnOnce( &mut T) -> R) -> Option<R> {
+        let object = self.get( id)?;
+        let mut object = object.try_borrow_mut().ok()?;
+
+        object.as_any_mut().downcast_mut::<T>().map( f)
+    }
+
+    pub fn iter( &self) -> impl Iterator<Item = (EntityId, &Rc<RefCell<dyn GameObject>>)> {
+        self.slots.iter().enumerate().filter_map( |(index, slot)| {
+            slot.object.as_ref().map( |object| (EntityId { index: index as u32, generation: slot.generation }, object))
+        })
+    }
+
+    pub fn len( &self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty( &self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn extend( &mut self, objects: Vec<Rc<RefCell<dyn GameObject>>>) {
+        objects.into_iter().for_each( |object| { self.insert( object); });
+    }
+
+    pub fn snapshot( &self) -> EntitiesSnapshot {
+        EntitiesSnapshot {
+            slots: self.slots.iter().map( |slot| (slot.generation, slot.object.as_ref().map( |object| object.borrow().snapshot()))).collect(),
+            free: self.free.clone(),
+        }
+    }
+
+    pub fn restore( snapshot: EntitiesSnapshot) -> Entities {
+        Entities {
+            slots: snapshot.slots.into_iter().map( |(generation, object)| Slot { generation, object: object.map( |object| object.into_object()) }).collect(),
+            free: snapshot.free,
+        }
+    }
+
+    // Removes all objects, handles to them stay invalid
+    pub fn clear( &mut self) {
+        let ids: Vec<EntityId> = self.iter().map( |(id, _)| id).collect();
+
+        ids.into_iter().for_each( |id| { self.remove( id); });
+    }
+
+    // Removes every object for which keep returns false
+    pub fn retain( &mut self, mut keep: impl FnMut( &dyn GameObject) -> bool) {
+        let removed: Vec<EntityId> = self.iter().filter( |(_, object)| !keep( &*object.borrow())).map( |(id, _)| id).collect();
+
+        removed.into_iter().for_each( |id| { self.remove( id); });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Asteroid, GameObjectFactory};
+    use crate::utils::ZERO;
+
+    fn asteroid() -> Rc<RefCell<dyn GameObject>> {
+        GameObjectFactory::with_placeholder_sprites().create_asteroid_small( ZERO, ZERO)
+    }
+
+    fn assert_stale( entities: &mut Entities, id: EntityId) {
+        assert!( entities.get( id).is_none());
+        assert!( !entities.contains( id));
+        assert!( entities.with_mut( id, |_: &mut Asteroid| ()).is_none());
+        assert!( entities.remove( id).is_none());
+    }
+
+    #[test]
+    fn stale_handles_after_reuse() {
+        let mut entities = Entities::new();
+        let old = entities.insert( asteroid());
+
+        assert!( entities.remove( old).is_some());
+        let new = entities.insert( asteroid());
+
+        // Same slot, newer generation
+        assert_eq!( new.index, old.index);
+        assert_ne!( new.generation, old.generation);
+        assert_stale( &mut entities, old);
+
+        // The stale remove above must not have freed the new object
+        assert!( entities.with_mut( new, |_: &mut Asteroid| ()).is_some());
+        assert_eq!( entities.len(), 1);
+    }
+
+    #[test]
+    fn retain_and_clear_invalidate_handles() {
+        let mut entities = Entities::new();
+        let dropped = entities.insert( asteroid());
+        let kept = entities.insert( asteroid());
+        entities.with_mut( dropped, |asteroid: &mut Asteroid| asteroid.expired = true);
+
+        entities.retain( |object| !object.is_expired());
+        assert_stale( &mut entities, dropped);
+        assert!( entities.contains( kept));
+
+        let reused = entities.insert( asteroid());
+        assert_eq!( reused.index, dropped.index);
+        assert_stale( &mut entities, dropped);
+
+        entities.clear();
+        assert!( entities.is_empty());
+        assert_stale( &mut entities, kept);
+        assert_stale( &mut entities, reused);
+
+        let ids: Vec<EntityId> = (0..2).map( |_| entities.insert( asteroid())).collect();
+        assert!( !ids.contains( &kept) && !ids.contains( &reused));
+    }
+}
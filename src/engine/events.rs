@@ -0,0 +1,65 @@
+use crate::utils::Vector;
+use crate::engine::GameObjectType;
+use crate::components::PowerUpKind;
+
+// Objects the game should create on behalf of another object
+#[derive(Clone, Debug, PartialEq)]
+pub enum Spawn {
+    Explosion { position: Vector },
+    BigExplosion { position: Vector },
+    PowerUp { position: Vector, kind: PowerUpKind },
+    // A power-up of a random kind, or nothing, at the drop rate of the current wave
+    PowerUpDrop { position: Vector },
+    AsteroidSmall { position: Vector, speed: Vector },
+    AsteroidMedium { position: Vector, speed: Vector },
+    Bullet { player: Option<usize>, team: Option<usize>, position: Vector, speed: Vector, color: String },
+    // Aimed at the nearest rocket by the game, starting clearance away from the saucer
+    SaucerBullet { position: Vector, clearance: f64, aim_error: f64 },
+    // Asteroids of the current round
+    Asteroids,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    // A bullet of the player hit something
+    Hit { player: usize, target: GameObjectType },
+    ScoreAwarded { player: usize, points: i32 },
+    Damaged { player: usize, amount: i32 },
+    Destroyed { objtype: GameObjectType, position: Vector },
+    SpawnRequested { spawn: Spawn },
+    RoundCleared { round: i32 },
+    // A destroyed rocket with lives left waited long enough
+    RespawnRequested { player: usize },
+}
+
+// Gets notified about every event the game processes, e.g. for audio, stats or achievements
+pub trait EventListener {
+    fn on_event( &mut self, event: &GameEvent);
+}
+
+#[derive(Default)]
+pub struct EventQueue {
+    events: Vec<GameEvent>,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        EventQueue { events: vec![] }
+    }
+
+    pub fn emit( &mut self, event: GameEvent) {
+        self.events.push( event);
+    }
+
+    pub fn spawn( &mut self, spawn: Spawn) {
+        self.emit( GameEvent::SpawnRequested { spawn });
+    }
+
+    pub fn drain( &mut self) -> Vec<GameEvent> {
+        std::mem::take( &mut self.events)
+    }
+
+    pub fn is_empty( &self) -> bool {
+        self.events.is_empty()
+    }
+}
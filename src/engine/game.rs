@@ -1,15 +1,46 @@
 use web_sys::{CanvasRenderingContext2d, HtmlImageElement};
 use js_sys::Date;
-use crate::utils::Vector;
+use crate::utils::{Vector, Random, ZERO};
+use crate::engine::renderer::Renderer;
+use crate::engine::canvas_renderer::CanvasRenderer;
+use crate::engine::entities::{Entities, EntityId};
+use crate::engine::events::{EventQueue, EventListener, GameEvent, Spawn};
+use crate::engine::broad_phase::SpatialHash;
+use crate::engine::state::GameState;
+use crate::engine::snapshot::{ObjectSnapshot, WorldSnapshot, SnapshotError, SNAPSHOT_VERSION};
+use crate::engine::replay::{Replay, ReplayCommand, ReplayRecorder, ReplayPlayer};
+use crate::engine::collision::{CollisionMatrix, CollisionResponse, LAYER_NONE};
+use crate::engine::bot::{Bot, BotDifficulty};
+use crate::engine::teams::{FriendlyFire, TEAM_COLORS, team_color};
+use crate::engine::game_mode::{GameMode, GameModeKind, MatchInfo, MatchOutcome, RocketInfo};
+use crate::engine::waves::{Wave, Waves};
+use crate::engine::hull::{polygons_overlap, polygon_overlaps_circle};
 use std::any::Any;
 use std::rc::Rc;
 use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
+use serde::{Serialize, Deserialize};
 use crate::components::GameObjectFactory;
-use crate::components::Rocket;
+use crate::components::{Rocket, PowerUpKind, SaucerSize};
+use crate::net::{RollbackSession, ServerConnection, WebSocketTransport};
+
+// Simulation runs at a fixed rate, independent of the display refresh rate
+pub const FIXED_DELTA_T: f64 = 1.0 / 120.0;
+// Upper bound for a single frame, so a long hitch does not trigger an avalanche of updates
+const MAX_FRAME_TIME: f64 = 0.25;
+// Moving further than this within one step means the object wrapped around the edges
+const MAX_INTERPOLATION_DISTANCE: f64 = 100.0;
+// Larger than the biggest object, so most objects occupy no more than four cells
+const COLLISION_CELL_SIZE: f64 = 64.0;
+// Random positions tried when looking for a safe spot to respawn a rocket
+const SPAWN_CANDIDATES: usize = 16;
+const SAUCER_BULLET_SPEED: f64 = 200.0;
+// Rocket colors of online players, by player index
+const ONLINE_COLORS: [&str; 4] = ["red", "blue", "green", "orange"];
+const BOT_COLOR: &str = "gray";
 
 #[wasm_bindgen]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct GamepadState {
     pub gamepad_index: usize,
     pub rocket_index: usize,
@@ -17,6 +48,7 @@ pub struct GamepadState {
     pub thrust: f64,
     pub shield: bool,
     pub fire: bool,
+    pub hyperspace: bool,
 }
 
 #[wasm_bindgen]
@@ -30,10 +62,17 @@ impl GamepadState {
             thrust: 0.0,
             shield: false,
             fire: false,
+            hyperspace: false,
         }
     }
 }
 
+impl Default for GamepadState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[wasm_bindgen]
 pub struct GamepadStates {
     inner: Vec<GamepadState>,
@@ -49,7 +88,7 @@ impl GamepadStates {
     }
 
     pub fn push(&mut self, state: &GamepadState) {
-        self.inner.push( state.clone());
+        self.inner.push( *state);
     }
 
     pub fn get(&self, index: usize) -> Option<GamepadState> {
@@ -65,7 +104,48 @@ impl GamepadStates {
     }
 }
 
-#[derive(PartialEq, Eq)]
+impl GamepadStates {
+    pub fn to_vec( &self) -> Vec<GamepadState> {
+        self.inner.clone()
+    }
+}
+
+impl From<Vec<GamepadState>> for GamepadStates {
+    fn from( inner: Vec<GamepadState>) -> Self {
+        GamepadStates { inner }
+    }
+}
+
+impl Default for GamepadStates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct ResetOptions {
+    // Keep the joined players with zeroed score and damage, otherwise everybody has to join again
+    pub keep_rockets: bool,
+}
+
+#[wasm_bindgen]
+impl ResetOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ResetOptions {
+        ResetOptions {
+            keep_rockets: true,
+        }
+    }
+}
+
+impl Default for ResetOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum GameObjectType {
     Asteroid,
     Rocket,
@@ -73,6 +153,8 @@ pub enum GameObjectType {
     Bullet,
     Announcer,
     Countdown,
+    PowerUp,
+    Saucer,
 }
 
 pub trait GameObject : Any {
@@ -80,37 +162,207 @@ pub trait GameObject : Any {
     fn as_any_mut( &mut self) -> &mut dyn Any;
 
     fn get_type( &self) -> GameObjectType;
+    fn snapshot( &self) -> ObjectSnapshot;
     fn current_position( &self) -> Vector;
     fn radius( &self) -> f64;
 
+    // Convex outline relative to current_position, for a closer test once the circles of radius touch.
+    // None collides as the circle.
+    fn hull( &self) -> Option<Vec<Vector>> {
+        None
+    }
+
+    // Layer the object is on and the layers it collides with, see engine::collision
+    fn collision_layer( &self) -> u32;
+    fn collision_mask( &self) -> u32;
+
     fn is_expired( &self) -> bool;
     fn expire( &mut self);
 
-    fn move_t( &mut self, delta_t: f64, game_area: Area);
-    fn collision_with( &mut self, objtype: GameObjectType, objfactory: &GameObjectFactory) -> Vec<Rc<RefCell<dyn GameObject>>>;
-    fn render( &self, ctx: &CanvasRenderingContext2d);
+    // Objects never modify each other directly, anything affecting the rest of the game is emitted as event
+    fn move_t( &mut self, delta_t: f64, game_area: Area, events: &mut EventQueue);
+    fn collision_with( &mut self, objtype: GameObjectType, rng: &mut Random, events: &mut EventQueue);
+    // interpolation is the fraction (0..1) of a simulation step passed since the last update
+    fn render( &self, renderer: &mut dyn Renderer, interpolation: f64);
+
+    // Hands out the power-up this object carries, at most once
+    fn take_power_up( &mut self) -> Option<PowerUpKind> {
+        None
+    }
+
+    fn collect( &mut self, _power_up: PowerUpKind, _events: &mut EventQueue) {
+    }
+
+    // Team of a rocket or of the rocket that fired a bullet, None for everything else
+    fn team( &self) -> Option<usize> {
+        None
+    }
+
+    // Damaging collision with an object of the same team, when friendly fire is DamageOnly
+    fn collision_with_teammate( &mut self, objtype: GameObjectType, rng: &mut Random, events: &mut EventQueue) {
+        self.collision_with( objtype, rng, events);
+    }
+
+    // Pushes the object away from another one, normal points from the other object towards this one
+    fn bounce( &mut self, normal: Vector) {
+        let velocity = self.velocity();
+
+        // Only when moving towards the other object, otherwise both would stick together
+        if velocity.dot( &normal) < 0.0 {
+            self.push( velocity.reflect( &normal).sub( &velocity), ZERO);
+        }
+    }
+
+    // Objects without mass do not take part in elastic collisions
+    fn mass( &self) -> f64 {
+        0.0
+    }
+
+    fn velocity( &self) -> Vector {
+        ZERO
+    }
+
+    // Result of an elastic collision
+    fn push( &mut self, _velocity_change: Vector, _offset: Vector) {
+    }
+
+    // Called before every simulation step, so objects can remember where they have been for rendering
+    fn store_previous_state( &mut self) {
+    }
+
+    // Position before the current simulation step, objects that move fast enough to skip over others must remember it
+    fn previous_position( &self) -> Vector {
+        self.current_position()
+    }
 
     fn distance( &self, other: &dyn GameObject) -> f64 {
         self.current_position().distance( &other.current_position())
     }
 }
 
+// Exact test after the circles touched, delta is the shortest vector from b to a across the edges of the screen.
+// Returns how deep the shapes overlap, None when only the circles do.
+fn shapes_overlap( a: &dyn GameObject, b: &dyn GameObject, delta: Vector, circle_overlap: f64) -> Option<f64> {
+    // Relative to the center of b, so objects on opposite edges of the screen line up
+    let hull_a = a.hull().map( |hull| hull.iter().map( |point| point.add( &delta)).collect::<Vec<Vector>>());
+
+    match (hull_a, b.hull()) {
+        (Some( hull_a), Some( hull_b)) => polygons_overlap( &hull_a, &hull_b),
+        (Some( hull_a), None) => polygon_overlaps_circle( &hull_a, ZERO, b.radius()),
+        (None, Some( hull_b)) => polygon_overlaps_circle( &hull_b, delta, a.radius()),
+        (None, None) => Some( circle_overlap),
+    }
+}
+
+// Where a was seen from b when both were closest during the last step. delta is where a is now, motion how far it
+// moved relative to b. Testing the circles there sweeps them along their paths (ray against circle), so fast objects
+// like bullets cannot skip over small ones between two steps.
+fn closest_approach( delta: Vector, motion: Vector) -> Vector {
+    let length = motion.dot( &motion);
+    if length == 0.0 {
+        return delta;
+    }
+
+    let start = delta.sub( &motion);
+    let t = (-start.dot( &motion) / length).clamp( 0.0, 1.0);
+
+    start.add( &motion.scale( t))
+}
+
+// Exchanges momentum along the normal (from b to a) like billiard balls do and moves both apart until they no longer
+// overlap, the lighter one further than the heavier one
+fn collide_elastic( a: &mut dyn GameObject, b: &mut dyn GameObject, normal: Vector, overlap: f64) {
+    let (mass_a, mass_b) = (a.mass(), b.mass());
+    if mass_a <= 0.0 || mass_b <= 0.0 {
+        return;
+    }
+
+    let total_mass = mass_a + mass_b;
+    let approach_speed = a.velocity().sub( &b.velocity()).dot( &normal);
+
+    // Already moving apart, e.g. after a collision in the previous step, only the overlap needs fixing
+    let impulse = if approach_speed < 0.0 { -2.0 * approach_speed * mass_a * mass_b / total_mass } else { 0.0 };
+
+    a.push( normal.scale( impulse / mass_a), normal.scale( overlap * mass_b / total_mass));
+    b.push( normal.scale( -impulse / mass_b), normal.scale( -overlap * mass_a / total_mass));
+}
+
+pub fn interpolate( previous: Vector, current: Vector, interpolation: f64) -> Vector {
+    if previous.distance( &current) > MAX_INTERPOLATION_DISTANCE {
+        return current;
+    }
+
+    previous.lerp( &current, interpolation)
+}
+
 #[derive(Clone, Copy)]
 pub struct Area {
     pub width: f64,
     pub height: f64,
 }
 
+impl Area {
+    // Shortest vector from b to a, taking into account that objects wrap around the edges
+    pub fn delta( &self, a: &Vector, b: &Vector) -> Vector {
+        let dx = (a.x - b.x).rem_euclid( self.width);
+        let dy = (a.y - b.y).rem_euclid( self.height);
+
+        Vector::new(
+            if dx > self.width / 2.0 { dx - self.width } else { dx },
+            if dy > self.height / 2.0 { dy - self.height } else { dy },
+        )
+    }
+
+    pub fn distance( &self, a: &Vector, b: &Vector) -> f64 {
+        self.delta( a, b).length()
+    }
+
+    pub fn contains( &self, position: &Vector) -> bool {
+        (0.0..=self.width).contains( &position.x) && (0.0..=self.height).contains( &position.y)
+    }
+}
+
 
 #[wasm_bindgen]
 pub struct Game {
+    state: GameState,
+    // State to return to when resuming
+    paused_state: GameState,
+    // Seconds spent in the current state
+    state_time: f64,
     round: i32,
+    mode: Box<dyn GameMode>,
+    waves: Waves,
+    // Seconds played in the current match, not counting countdowns and breaks
+    match_time: f64,
+    // How the last match ended, None while it is still going on
+    outcome: Option<MatchOutcome>,
+    // Seconds until the next saucer shows up
+    saucer_time: f64,
+    seed: u32,
+    rng: Random,
     game_area: Area,
-    ctx: CanvasRenderingContext2d,
+    renderer: Box<dyn Renderer>,
     time: i64,
+    accumulator: f64,
     objfactory: Rc<RefCell<GameObjectFactory>>,
-    shapes: Vec<Rc<RefCell<dyn GameObject>>>,
-    number_of_rockets: usize
+    shapes: Entities,
+    broad_phase: SpatialHash,
+    collision_matrix: CollisionMatrix,
+    // Rocket of every player that joined, None after the player left
+    players: Vec<Option<EntityId>>,
+    // Players controlled by the computer, their rockets are in players like all the others
+    bots: Vec<Bot>,
+    friendly_fire: FriendlyFire,
+    events: EventQueue,
+    recorder: Option<ReplayRecorder>,
+    playback: Option<ReplayPlayer>,
+    listeners: Vec<Box<dyn EventListener>>,
+    online: Option<RollbackSession>,
+    // A remote player is too far behind, the simulation waits for them
+    waiting_for_players: bool,
+    // Connected to an authoritative server, the world is whatever the server sent last
+    server: Option<ServerConnection>,
 }
 
 #[wasm_bindgen]
@@ -126,25 +378,322 @@ impl Game {
         rocket_thrust_off: HtmlImageElement,
         explosion_sprite: HtmlImageElement,
         rendering_context: CanvasRenderingContext2d,
+        seed: Option<u32>,
+        mode: Option<GameModeKind>,
     ) -> Game {
-        let object_factory = Rc::new(RefCell::new( GameObjectFactory::new(
-            ass,
-            ams,
-            als,
-            explosion_sprite,
-            rocket_thrust_on,
-            rocket_thrust_off,
-        )));
-        
-        Game {
-            round: 1,
-            game_area: Area { width: game_width, height: game_height },
-            ctx: rendering_context,
-            time: Self::now_ms(),
-            objfactory: object_factory,
-            shapes: vec![],
-            number_of_rockets: 0
+        let mut renderer = CanvasRenderer::new( rendering_context);
+        let object_factory = GameObjectFactory::new(
+            renderer.add_sprite( ass),
+            renderer.add_sprite( ams),
+            renderer.add_sprite( als),
+            renderer.add_sprite( explosion_sprite),
+            renderer.add_sprite( rocket_thrust_on),
+            renderer.add_sprite( rocket_thrust_off),
+        );
+
+        let seed = seed.unwrap_or_else( || Random::from_entropy().next_u64() as u32);
+        let mut game = Self::with_renderer( game_width, game_height, object_factory, Box::new( renderer), seed).with_mode( mode.unwrap_or_default());
+        game.time = Self::now_ms();
+
+        return game;
+    }
+
+    pub fn state( &self) -> GameState {
+        self.state
+    }
+
+    pub fn mode( &self) -> GameModeKind {
+        self.mode.kind()
+    }
+
+    // Title -> Lobby -> first round
+    pub fn start( &mut self) {
+        self.command( ReplayCommand::Start);
+    }
+
+    // Starts over with round 1, without reloading the page
+    pub fn reset( &mut self, options: Option<ResetOptions>) {
+        let options = options.unwrap_or_default();
+
+        self.command( ReplayCommand::Reset { keep_rockets: options.keep_rockets });
+    }
+
+    pub fn pause( &mut self) {
+        self.command( ReplayCommand::Pause);
+    }
+
+    pub fn resume( &mut self) {
+        self.command( ReplayCommand::Resume);
+    }
+
+    pub fn end_game( &mut self) {
+        self.command( ReplayCommand::EndGame);
+    }
+
+    // Returns the player index, which is used as rocket_index of the GamepadState
+    pub fn create_rocket( &mut self, color: String) -> usize {
+        if self.command( ReplayCommand::CreateRocket { color }) {
+            return self.players.len() - 1;
+        }
+
+        self.players.len()
+    }
+
+    // Lets a player leave, bullets still in flight keep flying but no longer score
+    pub fn remove_rocket( &mut self, rocket_index: usize) -> bool {
+        let exists = matches!( self.players.get( rocket_index), Some( Some( _)));
+
+        self.command( ReplayCommand::RemoveRocket { rocket_index }) && exists
+    }
+
+    // Puts the rocket into one of the TEAM_COLORS.len() teams, None lets it play for itself
+    pub fn set_team( &mut self, rocket_index: usize, team: Option<usize>) -> bool {
+        let exists = matches!( self.players.get( rocket_index), Some( Some( _)));
+        let valid = team.is_none_or( |team| team < TEAM_COLORS.len());
+
+        exists && valid && self.command( ReplayCommand::SetTeam { rocket_index, team })
+    }
+
+    pub fn set_friendly_fire( &mut self, friendly_fire: FriendlyFire) {
+        self.command( ReplayCommand::SetFriendlyFire { friendly_fire });
+    }
+
+    pub fn friendly_fire( &self) -> FriendlyFire {
+        self.friendly_fire
+    }
+
+    // Replaces the waves of asteroids, saucers and power-ups with JSON like assets/waves.json
+    pub fn load_waves( &mut self, json: &str) -> Result<(), JsValue> {
+        let waves = Waves::from_json( json).map_err( |e| JsValue::from_str( &e.to_string()))?;
+
+        self.command( ReplayCommand::SetWaves { waves });
+        Ok(())
+    }
+
+    // Adds a rocket flown by the computer, returns its player index like create_rocket
+    pub fn add_bot( &mut self, difficulty: BotDifficulty) -> usize {
+        if self.command( ReplayCommand::AddBot { difficulty }) {
+            return self.players.len() - 1;
+        }
+
+        self.players.len()
+    }
+
+    // Records the input of every frame from now on
+    pub fn start_recording( &mut self) {
+        self.recorder = Some( ReplayRecorder::new( self.world_snapshot()));
+    }
+
+    // Versioned JSON of the recording, None when nothing was recorded
+    pub fn stop_recording( &mut self) -> Option<String> {
+        self.recorder.take().map(|recorder| recorder.finish().to_json())
+    }
+
+    pub fn is_recording( &self) -> bool {
+        self.recorder.is_some()
+    }
+
+    // Plays a recording from stop_recording, live input is ignored until it is finished
+    pub fn play_replay( &mut self, replay: &str) -> Result<(), JsValue> {
+        let replay = Replay::from_json( replay).map_err( |e| JsValue::from_str( &e.to_string()))?;
+
+        self.start_replay( replay).map_err( |e| JsValue::from_str( &e.to_string()))
+    }
+
+    pub fn stop_replay( &mut self) {
+        self.playback = None;
+    }
+
+    pub fn is_replaying( &self) -> bool {
+        self.playback.is_some()
+    }
+
+    // Online match against other browsers connected to the same relay. Every peer has to use the same
+    // seed and number of players, local_player is different for each of them.
+    pub fn start_online( &mut self, url: &str, local_player: usize, number_of_players: usize) -> Result<(), JsValue> {
+        if local_player >= number_of_players {
+            return Err( JsValue::from_str( "local player must be smaller than the number of players"));
+        }
+
+        let transport = WebSocketTransport::connect( url)?;
+
+        self.playback = None;
+        self.recorder = None;
+        self.server = None;
+        self.start_session( RollbackSession::new( Box::new( transport), local_player, number_of_players));
+
+        Ok(())
+    }
+
+    pub fn is_online( &self) -> bool {
+        self.online.is_some()
+    }
+
+    // Plays on a server started with `cargo run --bin server`, this game no longer simulates anything itself
+    pub fn connect_to_server( &mut self, url: &str) -> Result<(), JsValue> {
+        let transport = WebSocketTransport::connect( url)?;
+
+        self.join_server( ServerConnection::new( Box::new( transport)));
+
+        Ok(())
+    }
+
+    pub fn is_connected_to_server( &self) -> bool {
+        self.server.is_some()
+    }
+
+    // Commands are recorded, so they can be replayed at the same moment; during playback only the recorded ones count
+    fn command( &mut self, command: ReplayCommand) -> bool {
+        // Online every peer has to see the same commands, so there are none
+        if self.playback.is_some() || self.online.is_some() {
+            return false;
+        }
+
+        if let Some( server) = &mut self.server {
+            server.send_command( command);
+            return false;
+        }
+
+        if let Some( recorder) = &mut self.recorder {
+            recorder.record_command( command.clone());
+        }
+
+        self.apply_command( command);
+
+        return true;
+    }
+
+    fn apply_command( &mut self, command: ReplayCommand) {
+        match command {
+            ReplayCommand::Start => self.start_game(),
+            ReplayCommand::Pause => self.pause_game(),
+            ReplayCommand::Resume => {
+                if self.state == GameState::Paused {
+                    self.state = self.paused_state;
+                }
+            }
+            ReplayCommand::EndGame => {
+                if self.state != GameState::Title {
+                    self.set_state( GameState::GameOver);
+                }
+            }
+            ReplayCommand::Reset { keep_rockets } => self.reset_game( keep_rockets),
+            ReplayCommand::CreateRocket { color } => { self.add_rocket( color); }
+            ReplayCommand::RemoveRocket { rocket_index } => self.remove_player( rocket_index),
+            ReplayCommand::AddBot { difficulty } => self.add_bot_player( difficulty),
+            ReplayCommand::SetTeam { rocket_index, team } => self.join_team( rocket_index, team),
+            ReplayCommand::SetFriendlyFire { friendly_fire } => self.friendly_fire = friendly_fire,
+            ReplayCommand::SetWaves { waves } => self.waves = waves,
+            ReplayCommand::SetCollisions { collision_matrix } => self.collision_matrix = collision_matrix,
+        }
+    }
+
+    fn start_game( &mut self) {
+        match self.state {
+            GameState::Title => self.set_state( GameState::Lobby),
+            GameState::Lobby => {
+                self.round = 1;
+                self.match_time = 0.0;
+                self.outcome = None;
+                self.reset_rockets();
+                self.start_round();
+            }
+            _ => {}
+        }
+    }
+
+    // Whatever happened while practicing in the lobby does not count for the match
+    fn reset_rockets( &mut self) {
+        let lives = self.mode.lives();
+        let ids: Vec<EntityId> = self.players.iter().flatten().copied().collect();
+
+        for id in ids {
+            let out_of_lives = self.shapes.with_mut( id, |rocket: &mut Rocket| {
+                let out_of_lives = rocket.is_out_of_lives();
+                rocket.score = 0;
+                rocket.damage = 0;
+                rocket.lives = lives;
+                out_of_lives
+            });
+
+            // Nothing would bring it back otherwise
+            if out_of_lives == Some( true) {
+                let position = self.safe_spawn_position();
+                self.shapes.with_mut( id, |rocket: &mut Rocket| rocket.respawn( position));
+            }
+        }
+    }
+
+    fn reset_game( &mut self, keep_rockets: bool) {
+        let colors: Vec<Option<(String, Option<usize>)>> = self.players.iter()
+            .map(|player| player.and_then(|id| self.shapes.with_mut( id, |rocket: &mut Rocket| (rocket.bullet_color.clone(), rocket.team))))
+            .collect();
+
+        self.shapes.clear();
+        self.events.drain();
+        self.players.clear();
+        self.round = 1;
+        self.match_time = 0.0;
+        self.outcome = None;
+        self.accumulator = 0.0;
+
+        if !keep_rockets {
+            self.bots.clear();
+        }
+
+        if keep_rockets {
+            // Recreate in the same order, so the rocket_index of every player stays the same
+            for color in colors {
+                match color {
+                    Some( (color, team)) => {
+                        let player = self.add_rocket( color);
+                        self.join_team( player, team);
+                    }
+                    None => self.players.push( None),
+                }
+            }
         }
+
+        if self.players.iter().any(|player| player.is_some()) {
+            self.set_state( GameState::Lobby);
+        }
+        else {
+            self.set_state( GameState::Title);
+        }
+    }
+
+    fn pause_game( &mut self) {
+        match self.state {
+            GameState::Lobby | GameState::Countdown | GameState::Playing | GameState::RoundOver => {
+                self.paused_state = self.state;
+                self.state = GameState::Paused;
+            }
+            _ => {}
+        }
+    }
+
+    // Versioned JSON of the complete world, e.g. to continue a match later
+    pub fn snapshot( &self) -> String {
+        self.world_snapshot().to_json()
+    }
+
+    pub fn restore( &mut self, snapshot: &str) -> Result<(), JsValue> {
+        let snapshot = WorldSnapshot::from_json( snapshot).map_err( |e| JsValue::from_str( &e.to_string()))?;
+
+        self.restore_world( snapshot).map_err( |e| JsValue::from_str( &e.to_string()))
+    }
+
+    // Same seed and same inputs result in the same match
+    pub fn seed( &self) -> u32 {
+        self.seed
+    }
+
+    // Replaces which objects collide and how with JSON like assets/collisions.json
+    pub fn load_collisions( &mut self, json: &str) -> Result<(), JsValue> {
+        let collision_matrix = CollisionMatrix::from_json( json).map_err( |e| JsValue::from_str( &e.to_string()))?;
+
+        self.set_collision_matrix( collision_matrix);
+        Ok(())
     }
 
     pub fn now_ms() -> i64 {
@@ -153,11 +702,9 @@ impl Game {
 
     pub fn animate_frame( &mut self, states: &GamepadStates)  -> Result<(), JsValue> {
         let now = Self::now_ms();
-        let delta_t = (now - self.time) as f64 / 1000.0;
+        let frame_time = (now - self.time) as f64 / 1000.0;
 
-        self.update_rockets( delta_t, states);
-        self.update_game_objects( delta_t);
-        self.check_collisions();
+        self.update( frame_time, states);
         self.render();
 
         self.time = now;
@@ -165,15 +712,184 @@ impl Game {
         return Ok(())
     }
 
-    pub fn create_rocket( &mut self, color: String) -> usize {
-        let position = Vector { x: (self.game_area.width / 3.0) + self.number_of_rockets as f64 * 50.0, y: 200.0 };
-        let score_position = Vector { x: 50.0 + self.number_of_rockets as f64 * 150.0, y: 50.0 };
-        let rocket = self.objfactory.borrow().create_rocket( position, score_position, color);
+    // Feeds the next recorded frame instead of the live input while a replay is playing
+    pub fn update( &mut self, frame_time: f64, states: &GamepadStates) {
+        if self.server.is_some() {
+            self.update_from_server( states);
+            return;
+        }
+
+        if let Some( playback) = &mut self.playback {
+            let frames = playback.due_frames( frame_time.clamp( 0.0, MAX_FRAME_TIME));
+            if playback.is_finished() {
+                self.playback = None;
+            }
+
+            for frame in frames {
+                for command in frame.commands {
+                    self.apply_command( command);
+                }
+
+                self.advance( frame.frame_time, &GamepadStates::from( frame.states));
+            }
+
+            return;
+        }
+
+        if let Some( recorder) = &mut self.recorder {
+            recorder.record_frame( frame_time, states.to_vec());
+        }
+
+        self.advance( frame_time, states);
+    }
+
+    fn update_from_server( &mut self, states: &GamepadStates) {
+        let Some( server) = &mut self.server else {
+            return;
+        };
+
+        if let Some( state) = states.get( 0) {
+            server.send_input( state);
+        }
+
+        if let Some( world) = server.receive() && self.restore_world( world).is_ok() {
+            // The server state is drawn as it is, without interpolating towards it
+            self.accumulator = FIXED_DELTA_T;
+        }
+    }
+
+    // Advances the simulation by the elapsed frame time in fixed steps, the remainder is carried over to the next frame
+    fn advance( &mut self, frame_time: f64, states: &GamepadStates) {
+        self.accumulator += frame_time.clamp( 0.0, MAX_FRAME_TIME);
+
+        while self.accumulator >= FIXED_DELTA_T {
+            match self.online.take() {
+                Some( mut session) => {
+                    // Rolling back restores the accumulator of an older frame, which has nothing to do with the time that passed
+                    let accumulator = self.accumulator;
+                    let local = states.get( 0).unwrap_or_default();
+
+                    self.waiting_for_players = !session.advance_frame( self, local);
+                    self.accumulator = accumulator;
+                    self.online = Some( session);
+                }
+                None => self.step( states),
+            }
+
+            self.accumulator -= FIXED_DELTA_T;
+        }
+    }
+
+    pub fn step( &mut self, states: &GamepadStates) {
+        self.state_time += FIXED_DELTA_T;
+
+        match self.state {
+            GameState::Title | GameState::Paused | GameState::GameOver => {}
+            GameState::Lobby | GameState::Countdown => self.step_world( states),
+            GameState::Playing => {
+                self.match_time += FIXED_DELTA_T;
+                self.step_world( states);
+                self.update_saucer_timer();
+            }
+            GameState::RoundOver => {
+                self.step_world( states);
+
+                if self.state_time > self.mode.round_over_time() {
+                    self.round += 1;
+                    self.start_round();
+                }
+            }
+        }
+
+        if matches!( self.state, GameState::Countdown | GameState::Playing | GameState::RoundOver) {
+            self.check_outcome();
+        }
+    }
+
+    fn check_outcome( &mut self) {
+        if let Some( outcome) = self.mode.outcome( &self.match_info()) {
+            self.outcome = Some( outcome);
+            self.set_state( GameState::GameOver);
+        }
+    }
+
+    fn match_info( &self) -> MatchInfo {
+        let rockets = self.players.iter().flatten()
+            .filter_map( |id| self.shapes.get( *id))
+            .filter_map( |object| {
+                let object = object.borrow();
+                let rocket = object.as_any().downcast_ref::<Rocket>()?;
+
+                Some( RocketInfo { player: rocket.player, team: rocket.team, score: rocket.score, out_of_lives: rocket.is_out_of_lives() })
+            })
+            .collect();
 
-        self.shapes.insert( self.number_of_rockets, rocket);
-        self.number_of_rockets += 1;
+        MatchInfo { round: self.round, time: self.match_time, rockets }
+    }
+
+    fn set_state( &mut self, state: GameState) {
+        self.state = state;
+        self.state_time = 0.0;
+    }
+
+    fn step_world( &mut self, states: &GamepadStates) {
+        self.shapes.iter().for_each(|(_, shape)| shape.borrow_mut().store_previous_state());
+
+        self.update_rockets( FIXED_DELTA_T, states);
+        self.update_bots( FIXED_DELTA_T);
+        self.update_game_objects( FIXED_DELTA_T);
+        self.check_collisions();
+        self.dispatch_events();
+    }
+
+    fn add_rocket( &mut self, color: String) -> usize {
+        let number_of_rockets = self.players.len();
+        let position = Vector { x: (self.game_area.width / 3.0) + number_of_rockets as f64 * 50.0, y: 200.0 };
+        let score_position = Vector { x: 50.0 + number_of_rockets as f64 * 150.0, y: 50.0 };
+        let rocket = self.objfactory.borrow().create_rocket( number_of_rockets, position, score_position, color);
+        let id = self.shapes.insert( rocket);
 
-        return self.number_of_rockets - 1;
+        self.shapes.with_mut( id, |rocket: &mut Rocket| rocket.lives = self.mode.lives());
+        self.players.push( Some( id));
+
+        return number_of_rockets;
+    }
+
+    fn remove_player( &mut self, rocket_index: usize) {
+        if let Some( Some( id)) = self.players.get( rocket_index).copied() {
+            self.players[rocket_index] = None;
+            self.shapes.remove( id);
+        }
+
+        self.bots.retain( |bot| bot.player != rocket_index);
+    }
+
+    fn join_team( &mut self, rocket_index: usize, team: Option<usize>) {
+        if let Some( Some( id)) = self.players.get( rocket_index).copied() {
+            self.shapes.with_mut( id, |rocket: &mut Rocket| rocket.team = team);
+        }
+    }
+
+    fn add_bot_player( &mut self, difficulty: BotDifficulty) {
+        let player = self.add_rocket( BOT_COLOR.to_string());
+
+        self.bots.push( Bot::new( player, difficulty));
+    }
+
+    // Bots look at the world and then fly their rockets with the same input a gamepad gives
+    fn update_bots( &mut self, delta_t: f64) {
+        // In co-op shooting other rockets only costs lives
+        let hunt_rockets = self.mode.points( GameObjectType::Rocket) != 0;
+        let states: Vec<GamepadState> = self.bots.iter_mut()
+            .filter_map( |bot| {
+                let id = self.players.get( bot.player).copied().flatten()?;
+                Some( bot.control( id, &self.shapes, self.game_area, hunt_rockets, delta_t))
+            })
+            .collect();
+
+        for state in states {
+            self.update_rocket( delta_t, &state);
+        }
     }
 
     fn update_rockets( &mut self, delta_t: f64, states: &GamepadStates) {
@@ -185,65 +901,788 @@ impl Game {
     }
 
     fn update_rocket( &mut self, delta_t: f64, state: &GamepadState) {
-        let mut bullets: Vec<Rc<RefCell<dyn GameObject>>> = vec![];
+        let Some( Some( id)) = self.players.get( state.rocket_index).copied() else {
+            return;
+        };
 
-        if let Some( rocket) = self.shapes[state.rocket_index].borrow_mut().as_any_mut().downcast_mut::<Rocket>() {
-            bullets.extend( rocket.update( delta_t, state, &self.objfactory.borrow()));
-        }
-
-        self.shapes.extend( bullets);
+        self.shapes.with_mut( id, |rocket: &mut Rocket| rocket.update( delta_t, state, self.game_area, &mut self.rng, &mut self.events));
     }
 
     fn clean_shapes( &mut self) {
-        self.shapes.retain( |x| !x.borrow().is_expired());
+        self.shapes.retain( |x| !x.is_expired());
 
-        let nof_asteroids = self.shapes.iter().filter(|obj| obj.borrow().get_type() == GameObjectType::Asteroid).count();
-        let nof_countdowns = self.shapes.iter().filter(|obj| obj.borrow().get_type() == GameObjectType::Countdown).count();
-        if nof_asteroids == 0 && nof_countdowns == 0{
-            self.start_new_round();
+        let nof_asteroids = self.shapes.iter().filter(|(_, obj)| obj.borrow().get_type() == GameObjectType::Asteroid).count();
+        if self.state == GameState::Playing && self.mode.round_cleared( nof_asteroids) {
+            self.events.emit( GameEvent::RoundCleared { round: self.round });
         }
     }
 
-    fn start_new_round( &mut self) {
+    fn start_round( &mut self) {
         let position = Vector { x: self.game_area.width / 2.0 - 20.0, y: self.game_area.height / 2.0 - 8.0 };
-        let game = self as *mut Self;
+        let countdown = self.objfactory.borrow().create_countdown( position, 6);
 
-        self.shapes.push( self.objfactory.borrow().create_countdown( game, position, 6));
+        self.shapes.insert( countdown);
+        self.set_state( GameState::Countdown);
+        self.saucer_time = self.wave().saucer_interval.unwrap_or( 0.0);
+    }
 
-        self.round += 1;
+    fn wave( &self) -> Wave {
+        self.mode.wave( &self.waves, self.round)
+    }
+
+    fn update_saucer_timer( &mut self) {
+        let wave = self.wave();
+        let Some( interval) = wave.saucer_interval else {
+            return;
+        };
+
+        self.saucer_time -= FIXED_DELTA_T;
+        if self.saucer_time > 0.0 {
+            return;
+        }
+
+        self.saucer_time = interval;
+
+        let size = if self.rng.random_number() < wave.small_saucer_chance { SaucerSize::Small } else { SaucerSize::Large };
+        let from_left = self.rng.random_number() < 0.5;
+        let x = if from_left { -size.radius() + 1.0 } else { self.game_area.width + size.radius() - 1.0 };
+        let position = Vector::new( x, self.rng.random_number_max( self.game_area.height));
+
+        let saucer = self.objfactory.borrow().create_saucer( size, position, from_left);
+        self.shapes.insert( saucer);
+    }
+
+    // Bullet towards the nearest rocket that can be hit, off by up to aim_error radians
+    fn spawn_saucer_bullet( &mut self, position: Vector, clearance: f64, aim_error: f64) -> Option<Rc<RefCell<dyn GameObject>>> {
+        let target = self.shapes.iter()
+            .map( |(_, object)| object.borrow())
+            .filter( |object| object.get_type() == GameObjectType::Rocket && object.collision_layer() != LAYER_NONE)
+            .map( |object| self.game_area.delta( &object.current_position(), &position))
+            .min_by( |a, b| a.length().total_cmp( &b.length()))?;
+
+        let direction = target.normalize().rotate( (self.rng.random_number() * 2.0 - 1.0) * aim_error);
+        let start = position.add( &direction.scale( clearance + 4.0));
+
+        Some( self.objfactory.borrow().create_bullet( None, None, start, direction.scale( SAUCER_BULLET_SPEED), "black".to_string()))
     }
 
     pub fn spawn_asteroids( &mut self) {
-        self.shapes.extend( self.objfactory.borrow().create_asteroids( self.round * 2, self.game_area, self.round as f64 * 50.0));
+        let wave = self.wave();
+
+        self.shapes.extend( self.objfactory.borrow().create_asteroids( &mut self.rng, &wave, self.game_area));
     }
 
     fn update_game_objects( &mut self, delta_t : f64) {
-        self.shapes.iter_mut().for_each(|shape| shape.borrow_mut().move_t( delta_t, self.game_area));
+        self.shapes.iter().for_each(|(_, shape)| shape.borrow_mut().move_t( delta_t, self.game_area, &mut self.events));
+        self.dispatch_events();
         self.clean_shapes();
     }
 
     fn check_collisions( &mut self) {
-        let mut objects : Vec<Rc<RefCell<dyn GameObject>>> = vec![];
-
-        let len = self.shapes.len();
-        for i in 0..len {
-            for j in (i + 1)..len {
-                let (left, right) = self.shapes.split_at_mut(j);
-                let obj1 = &left[i];
-                let obj2 = &right[0];
-
-                if obj1.borrow().distance( &*obj2.borrow()) < (obj1.borrow().radius() + obj2.borrow().radius()) {
-                    objects.extend( obj1.borrow_mut().collision_with( obj2.borrow().get_type(), &self.objfactory.borrow()));
-                    objects.extend( obj2.borrow_mut().collision_with( obj1.borrow().get_type(), &self.objfactory.borrow()));
+        let shapes : Vec<Rc<RefCell<dyn GameObject>>> = self.shapes.iter().map(|(_, shape)| shape.clone()).collect();
+
+        self.broad_phase.clear();
+        for (index, shape) in shapes.iter().enumerate() {
+            let shape = shape.borrow();
+
+            // Explosions, countdowns etc. are on no layer and collide with nothing. An invulnerable rocket is on no
+            // layer either, but still collides with the power-ups it picks up.
+            if shape.collision_layer() != LAYER_NONE || shape.collision_mask() != LAYER_NONE {
+                // Covering the whole path of the last step
+                let motion = self.step_motion( &*shape);
+                let center = shape.current_position().sub( &motion.scale( 0.5));
+
+                self.broad_phase.insert( index, center, shape.radius() + motion.length() / 2.0);
+            }
+        }
+
+        for (i, j) in self.broad_phase.candidate_pairs() {
+            let obj1 = &shapes[i];
+            let obj2 = &shapes[j];
+
+            if !self.may_collide( &*obj1.borrow(), &*obj2.borrow()) {
+                continue;
+            }
+
+            // Already destroyed by an earlier pair of this step, e.g. a bullet that has hit another asteroid
+            if obj1.borrow().is_expired() || obj2.borrow().is_expired() {
+                continue;
+            }
+
+            let delta = self.game_area.delta( &obj1.borrow().current_position(), &obj2.borrow().current_position());
+            let radii = obj1.borrow().radius() + obj2.borrow().radius();
+
+            // Apart now, but they may have passed through each other during the step
+            let delta = if delta.length() < radii {
+                delta
+            } else {
+                closest_approach( delta, self.step_motion( &*obj1.borrow()).sub( &self.step_motion( &*obj2.borrow())))
+            };
+
+            let circle_overlap = radii - delta.length();
+            if circle_overlap <= 0.0 {
+                continue;
+            }
+
+            let overlap = shapes_overlap( &*obj1.borrow(), &*obj2.borrow(), delta, circle_overlap);
+            if let Some( overlap) = overlap {
+                let normal = delta.normalize();
+
+                if self.is_elastic( &*obj1.borrow(), &*obj2.borrow()) {
+                    collide_elastic( &mut *obj1.borrow_mut(), &mut *obj2.borrow_mut(), normal, overlap);
+                    continue;
+                }
+
+                self.respond_to_collision( obj1, obj2, normal);
+                self.respond_to_collision( obj2, obj1, normal.scale( -1.0));
+            }
+        }
+    }
+
+    // Distance the object moved during the last step, nothing when it wrapped around the edges or was respawned
+    fn step_motion( &self, shape: &dyn GameObject) -> Vector {
+        let motion = self.game_area.delta( &shape.current_position(), &shape.previous_position());
+        if motion.length() > MAX_INTERPOLATION_DISTANCE {
+            return ZERO;
+        }
+
+        return motion;
+    }
+
+    fn may_collide( &self, obj1: &dyn GameObject, obj2: &dyn GameObject) -> bool {
+        let layers_match = (obj1.collision_mask() & obj2.collision_layer()) != 0 || (obj2.collision_mask() & obj1.collision_layer()) != 0;
+
+        layers_match && self.collision_matrix.interacts( obj1.get_type(), obj2.get_type())
+            && self.on_screen( obj1) && self.on_screen( obj2)
+    }
+
+    // Saucers fly in and out past the edges without wrapping, the wrapped distance would put them next to
+    // objects on the opposite side of the screen
+    fn on_screen( &self, object: &dyn GameObject) -> bool {
+        object.get_type() != GameObjectType::Saucer || self.game_area.contains( &object.current_position())
+    }
+
+    // Elastic collisions involve both objects at once, the other responses are applied to each of them on its own
+    fn is_elastic( &self, obj1: &dyn GameObject, obj2: &dyn GameObject) -> bool {
+        self.collision_matrix.response( obj1.get_type(), obj2.get_type()) == CollisionResponse::Elastic
+            && self.collision_matrix.response( obj2.get_type(), obj1.get_type()) == CollisionResponse::Elastic
+    }
+
+    fn respond_to_collision( &mut self, object: &Rc<RefCell<dyn GameObject>>, other: &Rc<RefCell<dyn GameObject>>, normal: Vector) {
+        if (object.borrow().collision_mask() & other.borrow().collision_layer()) == 0 {
+            return;
+        }
+
+        let other_type = other.borrow().get_type();
+        let response = self.collision_matrix.response( object.borrow().get_type(), other_type);
+
+        let teammates = object.borrow().team().is_some() && object.borrow().team() == other.borrow().team();
+
+        match response {
+            CollisionResponse::Damage if teammates => match self.friendly_fire {
+                FriendlyFire::Off => {}
+                FriendlyFire::DamageOnly => object.borrow_mut().collision_with_teammate( other_type, &mut self.rng, &mut self.events),
+                FriendlyFire::Full => object.borrow_mut().collision_with( other_type, &mut self.rng, &mut self.events),
+            },
+            CollisionResponse::Damage => object.borrow_mut().collision_with( other_type, &mut self.rng, &mut self.events),
+            CollisionResponse::Bounce => object.borrow_mut().bounce( normal),
+            CollisionResponse::Collect => {
+                let power_up = other.borrow_mut().take_power_up();
+
+                if let Some( power_up) = power_up {
+                    object.borrow_mut().collect( power_up, &mut self.events);
                 }
             }
+            CollisionResponse::Elastic | CollisionResponse::PassThrough => {}
+        }
+    }
+
+    fn dispatch_events( &mut self) {
+        // Handling an event may emit further events
+        while !self.events.is_empty() {
+            for event in self.events.drain() {
+                self.listeners.iter_mut().for_each(|listener| listener.on_event( &event));
+                self.handle_event( event);
+            }
+        }
+    }
+
+    fn handle_event( &mut self, event: GameEvent) {
+        match event {
+            GameEvent::ScoreAwarded { player, points } => {
+                // The player may have left the game in the meantime
+                if let Some( Some( id)) = self.players.get( player).copied() {
+                    self.shapes.with_mut( id, |rocket: &mut Rocket| rocket.score += points);
+                }
+            }
+            GameEvent::SpawnRequested { spawn } => {
+                self.spawn( spawn);
+            }
+            GameEvent::RoundCleared { .. } => {
+                self.set_state( GameState::RoundOver);
+            }
+            GameEvent::RespawnRequested { player } => {
+                let position = self.safe_spawn_position();
+
+                if let Some( Some( id)) = self.players.get( player).copied() {
+                    self.shapes.with_mut( id, |rocket: &mut Rocket| rocket.respawn( position));
+                }
+            }
+            GameEvent::Hit { player, target } => {
+                let points = self.mode.points( target);
+                let scoring = matches!( self.state, GameState::Countdown | GameState::Playing | GameState::RoundOver);
+
+                if scoring && points != 0 {
+                    self.events.emit( GameEvent::ScoreAwarded { player, points });
+                }
+            }
+            GameEvent::Damaged { .. } | GameEvent::Destroyed { .. } => {}
+        }
+    }
+
+    // The best of a few random positions, the one furthest away from asteroids and other rockets
+    fn safe_spawn_position( &mut self) -> Vector {
+        let dangers: Vec<(Vector, f64)> = self.shapes.iter()
+            .map( |(_, object)| object.borrow())
+            .filter( |object| matches!( object.get_type(), GameObjectType::Asteroid | GameObjectType::Rocket) && object.collision_layer() != LAYER_NONE)
+            .map( |object| (object.current_position(), object.radius()))
+            .collect();
+
+        let mut best = Vector::new( self.game_area.width / 2.0, self.game_area.height / 2.0);
+        let mut best_clearance = f64::MIN;
+
+        for _ in 0..SPAWN_CANDIDATES {
+            let candidate = Vector::new( self.rng.random_number_max( self.game_area.width), self.rng.random_number_max( self.game_area.height));
+            let clearance = dangers.iter()
+                .map( |(position, radius)| self.game_area.distance( &candidate, position) - radius)
+                .fold( f64::MAX, f64::min);
+
+            if clearance > best_clearance {
+                best = candidate;
+                best_clearance = clearance;
+            }
+        }
+
+        return best;
+    }
+
+    fn spawn( &mut self, spawn: Spawn) {
+        let object = match spawn {
+            Spawn::Explosion { position } => self.objfactory.borrow().create_explosion( position),
+            Spawn::BigExplosion { position } => self.objfactory.borrow().create_big_explosion( position),
+            Spawn::PowerUp { position, kind } => self.objfactory.borrow().create_power_up( position, kind),
+            Spawn::PowerUpDrop { position } => {
+                if self.rng.random_number() >= self.wave().power_up_chance {
+                    return;
+                }
+
+                self.objfactory.borrow().create_power_up( position, PowerUpKind::random( &mut self.rng))
+            }
+            Spawn::AsteroidSmall { position, speed } => self.objfactory.borrow().create_asteroid_small( position, speed),
+            Spawn::AsteroidMedium { position, speed } => self.objfactory.borrow().create_asteroid_medium( position, speed),
+            Spawn::Bullet { player, team, position, speed, color } => self.objfactory.borrow().create_bullet( player, team, position, speed, color),
+            Spawn::SaucerBullet { position, clearance, aim_error } => {
+                match self.spawn_saucer_bullet( position, clearance, aim_error) {
+                    Some( bullet) => bullet,
+                    None => return,
+                }
+            }
+            Spawn::Asteroids => {
+                self.spawn_asteroids();
+                self.set_state( GameState::Playing);
+                return;
+            }
+        };
+
+        self.shapes.insert( object);
+    }
+
+    pub fn render( &mut self) {
+        self.renderer.clear( self.game_area.width, self.game_area.height);
+
+        match self.state {
+            GameState::Title => {
+                self.render_message( "ROCKETS!", "Press Enter to start");
+            }
+            GameState::Lobby => {
+                self.render_world( self.accumulator / FIXED_DELTA_T);
+                self.render_message( "Waiting for players", "Connect your gamepads, press Enter when everybody joined");
+            }
+            GameState::Countdown | GameState::Playing if self.waiting_for_players => {
+                self.render_world( 1.0);
+                self.render_message( "Waiting for players", "The connection to another player is lagging");
+            }
+            GameState::Countdown | GameState::Playing => {
+                self.render_world( self.accumulator / FIXED_DELTA_T);
+            }
+            GameState::RoundOver => {
+                self.render_world( self.accumulator / FIXED_DELTA_T);
+                self.render_message( &format!( "Round {} cleared", self.round), "");
+            }
+            // Nothing moves, so there is nothing to interpolate
+            GameState::Paused => {
+                self.render_world( 1.0);
+                self.render_message( "Paused", "Press P to continue");
+            }
+            GameState::GameOver => {
+                self.render_world( 1.0);
+                let title = match self.outcome {
+                    Some( MatchOutcome::Winner { player }) => format!( "Player {} wins", player + 1),
+                    Some( MatchOutcome::WinningTeam { team }) => format!( "Team {} wins", team + 1),
+                    Some( MatchOutcome::Draw) => "Draw".to_string(),
+                    Some( MatchOutcome::Defeated) | None => "Game over".to_string(),
+                };
+                self.render_message( &title, &format!( "Reached round {}, press R to play again", self.round));
+            }
+        }
+    }
+
+    fn render_world( &mut self, interpolation: f64) {
+        self.shapes.iter().for_each(|(_, shape)| shape.borrow().render( &mut *self.renderer, interpolation));
+        self.render_team_scores();
+
+        if let Some( status) = self.mode.status( &self.match_info()) {
+            self.renderer.fill_text( &status, self.game_area.width / 2.0 - 60.0, 30.0, "20px sans-serif", "black");
+        }
+    }
+
+    // Sum of the scores of every team, in the top right corner
+    fn render_team_scores( &mut self) {
+        let mut totals: Vec<Option<i32>> = vec![None; TEAM_COLORS.len()];
+
+        for id in self.players.iter().flatten() {
+            let Some( object) = self.shapes.get( *id) else {
+                continue;
+            };
+
+            if let Some( rocket) = object.borrow().as_any().downcast_ref::<Rocket>()
+                && let Some( team) = rocket.team
+                && let Some( total) = totals.get_mut( team)
+            {
+                *total = Some( total.unwrap_or( 0) + rocket.score);
+            }
+        }
+
+        let x = self.game_area.width - 180.0;
+        let mut y = 50.0;
+        for (team, total) in totals.iter().enumerate() {
+            if let Some( total) = total {
+                self.renderer.fill_text( &format!( "Team {}: {}", team + 1, total), x, y, "20px sans-serif", team_color( team));
+                y += 25.0;
+            }
+        }
+    }
+
+    fn render_message( &mut self, title: &str, subtitle: &str) {
+        let x = self.game_area.width / 2.0 - 200.0;
+        let y = self.game_area.height / 2.0;
+
+        self.renderer.fill_text( title, x, y - 30.0, "48px sans-serif", "black");
+        self.renderer.fill_text( subtitle, x, y + 20.0, "20px sans-serif", "black");
+    }
+}
+
+impl Game {
+    pub fn with_renderer( game_width: f64, game_height: f64, objfactory: GameObjectFactory, renderer: Box<dyn Renderer>, seed: u32) -> Game {
+        Game {
+            state: GameState::Title,
+            paused_state: GameState::Title,
+            state_time: 0.0,
+            round: 1,
+            mode: GameModeKind::default().create(),
+            waves: Waves::default(),
+            match_time: 0.0,
+            outcome: None,
+            saucer_time: 0.0,
+            seed,
+            rng: Random::new( seed as u64),
+            game_area: Area { width: game_width, height: game_height },
+            renderer,
+            time: 0,
+            accumulator: 0.0,
+            objfactory: Rc::new( RefCell::new( objfactory)),
+            shapes: Entities::new(),
+            broad_phase: SpatialHash::new( Area { width: game_width, height: game_height }, COLLISION_CELL_SIZE),
+            collision_matrix: CollisionMatrix::default(),
+            players: vec![],
+            bots: vec![],
+            friendly_fire: FriendlyFire::default(),
+            events: EventQueue::new(),
+            recorder: None,
+            playback: None,
+            listeners: vec![],
+            online: None,
+            waiting_for_players: false,
+            server: None,
+        }
+    }
+
+    pub fn world_snapshot( &self) -> WorldSnapshot {
+        WorldSnapshot {
+            version: SNAPSHOT_VERSION,
+            state: self.state,
+            paused_state: self.paused_state,
+            state_time: self.state_time,
+            round: self.round,
+            seed: self.seed,
+            rng: self.rng,
+            accumulator: self.accumulator,
+            saucer_time: self.saucer_time,
+            players: self.players.clone(),
+            bots: self.bots.clone(),
+            friendly_fire: self.friendly_fire,
+            mode: self.mode.kind(),
+            waves: self.waves.clone(),
+            collision_matrix: self.collision_matrix.clone(),
+            match_time: self.match_time,
+            outcome: self.outcome,
+            entities: self.shapes.snapshot(),
+        }
+    }
+
+    pub fn restore_world( &mut self, snapshot: WorldSnapshot) -> Result<(), SnapshotError> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err( SnapshotError::UnsupportedVersion( snapshot.version));
+        }
+
+        // A save edited by hand could put rockets into teams that do not exist
+        let unknown_team = snapshot.entities.objects().find_map( |object| match object {
+            ObjectSnapshot::Rocket( rocket) => rocket.team.filter( |team| *team >= TEAM_COLORS.len()),
+            _ => None,
+        });
+
+        if let Some( team) = unknown_team {
+            return Err( SnapshotError::Invalid( format!( "team {} does not exist", team + 1)));
+        }
+
+        self.state = snapshot.state;
+        self.paused_state = snapshot.paused_state;
+        self.state_time = snapshot.state_time;
+        self.round = snapshot.round;
+        self.seed = snapshot.seed;
+        self.rng = snapshot.rng;
+        self.accumulator = snapshot.accumulator;
+        self.saucer_time = snapshot.saucer_time;
+        self.players = snapshot.players;
+        self.bots = snapshot.bots;
+        self.friendly_fire = snapshot.friendly_fire;
+        self.mode = snapshot.mode.create();
+        self.waves = snapshot.waves;
+        self.collision_matrix = snapshot.collision_matrix;
+        self.match_time = snapshot.match_time;
+        self.outcome = snapshot.outcome;
+        self.shapes = Entities::restore( snapshot.entities);
+        self.events.drain();
+
+        Ok(())
+    }
+
+    // Starts a new match with a rocket for every player of the session, every peer ends up in the same world
+    pub fn start_session( &mut self, session: RollbackSession) {
+        self.reset_game( false);
+        self.rng = Random::new( self.seed as u64);
+
+        for player in 0..session.number_of_players() {
+            self.add_rocket( ONLINE_COLORS[player % ONLINE_COLORS.len()].to_string());
+        }
+
+        // Title -> Lobby -> first round, nobody else is going to join
+        self.start_game();
+        self.start_game();
+        self.server = None;
+        self.online = Some( session);
+        self.waiting_for_players = false;
+    }
+
+    pub fn join_server( &mut self, connection: ServerConnection) {
+        self.playback = None;
+        self.recorder = None;
+        self.online = None;
+        self.server = Some( connection);
+    }
+
+    pub fn server_connection( &self) -> Option<&ServerConnection> {
+        self.server.as_ref()
+    }
+
+    pub fn session( &self) -> Option<&RollbackSession> {
+        self.online.as_ref()
+    }
+
+    pub fn start_replay( &mut self, replay: Replay) -> Result<(), SnapshotError> {
+        self.recorder = None;
+        self.restore_world( replay.start.clone())?;
+        self.playback = Some( ReplayPlayer::new( replay));
+
+        Ok(())
+    }
+
+    // Decides which object types interact and how, e.g. to let bullets pass through rockets
+    pub fn collision_matrix( &self) -> &CollisionMatrix {
+        &self.collision_matrix
+    }
+
+    pub fn set_collision_matrix( &mut self, collision_matrix: CollisionMatrix) {
+        self.command( ReplayCommand::SetCollisions { collision_matrix });
+    }
+
+    pub fn add_listener( &mut self, listener: Box<dyn EventListener>) {
+        self.listeners.push( listener);
+    }
+
+    // Game without a browser, e.g. for native builds and tests
+    pub fn headless( game_width: f64, game_height: f64, renderer: Box<dyn Renderer>, seed: u32) -> Game {
+        Self::with_renderer( game_width, game_height, GameObjectFactory::with_placeholder_sprites(), renderer, seed)
+    }
+
+    // Rules of the matches, choose them before the players join, they decide the lives of the rockets
+    pub fn with_mode( mut self, mode: GameModeKind) -> Game {
+        self.mode = mode.create();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::NullRenderer;
+
+    // In the lobby the world runs, but no asteroids or saucers show up on their own
+    fn lobby() -> Game {
+        let mut game = Game::headless( 800.0, 600.0, Box::new( NullRenderer), 1);
+        game.start();
+
+        game
+    }
+
+    // Objects of a type still in the game, expired ones are only removed in the next step
+    fn count( game: &Game, objtype: GameObjectType) -> usize {
+        game.shapes.iter().filter( |(_, shape)| shape.borrow().get_type() == objtype && !shape.borrow().is_expired()).count()
+    }
+
+    #[test]
+    fn bullets_do_not_hit_across_the_edges() {
+        let mut game = lobby();
+        let asteroid = game.objfactory.borrow().create_asteroid_small( Vector::new( 795.0, 300.0), ZERO);
+        let bullet = game.objfactory.borrow().create_bullet( Some( 0), None, Vector::new( -5.0, 300.0), Vector::new( -250.0, 0.0), "red".to_string());
+        game.shapes.insert( asteroid);
+        game.shapes.insert( bullet);
+
+        game.step( &GamepadStates::new());
+
+        assert_eq!( count( &game, GameObjectType::Asteroid), 1);
+        assert_eq!( count( &game, GameObjectType::Bullet), 0);
+    }
+
+    #[test]
+    fn invulnerable_rockets_collect_power_ups() {
+        let mut game = lobby();
+        let position = Vector::new( 400.0, 300.0);
+        let rocket = game.objfactory.borrow().create_rocket( 0, position, ZERO, "red".to_string());
+        let power_up = game.objfactory.borrow().create_power_up( position, PowerUpKind::RapidFire);
+        let id = game.shapes.insert( rocket);
+        game.shapes.insert( power_up);
+        game.shapes.with_mut( id, |rocket: &mut Rocket| rocket.invulnerable_time = 1.0);
+
+        game.step( &GamepadStates::new());
+
+        assert!( game.shapes.with_mut( id, |rocket: &mut Rocket| rocket.rapid_fire_time > 0.0).unwrap());
+    }
+
+    #[test]
+    fn saucers_do_not_shoot_saucers() {
+        let mut game = lobby();
+        let position = Vector::new( 400.0, 300.0);
+        let saucer = game.objfactory.borrow().create_saucer( SaucerSize::Large, position, true);
+        let bullet = game.objfactory.borrow().create_bullet( None, None, position, ZERO, "black".to_string());
+        game.shapes.insert( saucer);
+        game.shapes.insert( bullet);
+
+        game.step( &GamepadStates::new());
+
+        assert_eq!( count( &game, GameObjectType::Saucer), 1);
+        assert_eq!( count( &game, GameObjectType::Bullet), 1);
+    }
+
+    #[test]
+    fn saucers_off_screen_do_not_hit_across_the_edges() {
+        let mut game = lobby();
+        let saucer = game.objfactory.borrow().create_saucer( SaucerSize::Large, Vector::new( -23.0, 300.0), true);
+        let rocket = game.objfactory.borrow().create_rocket( 0, Vector::new( 795.0, 300.0), ZERO, "red".to_string());
+        let id = game.shapes.insert( rocket);
+        game.shapes.insert( saucer);
+
+        game.step( &GamepadStates::new());
+
+        assert_eq!( count( &game, GameObjectType::Saucer), 1);
+        assert_eq!( game.shapes.with_mut( id, |rocket: &mut Rocket| rocket.damage), Some( 0));
+    }
+
+    #[test]
+    fn co_op_bots_leave_humans_alone() {
+        let mut game = Game::headless( 800.0, 600.0, Box::new( NullRenderer), 1).with_mode( GameModeKind::CoopSurvival);
+        game.start();
+        let player = game.create_rocket( "red".to_string());
+        let id = game.players[player].unwrap();
+        game.add_bot( BotDifficulty::Hard);
+
+        for _ in 0..1200 {
+            game.step( &GamepadStates::new());
         }
 
-        self.shapes.extend(objects);
+        assert_eq!( game.shapes.with_mut( id, |rocket: &mut Rocket| (rocket.lives, rocket.damage)), Some( (game.mode.lives(), 0)));
+    }
+
+    #[test]
+    fn snapshots_with_unknown_teams_are_rejected() {
+        let mut game = lobby();
+        let rocket = game.create_rocket( "red".to_string());
+        let id = game.players[rocket].unwrap();
+        game.shapes.with_mut( id, |rocket: &mut Rocket| rocket.team = Some( TEAM_COLORS.len()));
+
+        // Neither drawn nor restored
+        game.render();
+        assert!( matches!( game.restore_world( game.world_snapshot()), Err( SnapshotError::Invalid( _))));
+    }
+
+    // Sum of m·v over objects
+    fn momentum( objects: &[&dyn GameObject]) -> Vector {
+        objects.iter().fold( ZERO, |sum, object| sum.add( &object.velocity().scale( object.mass())))
+    }
+
+    fn assert_close( a: Vector, b: Vector) {
+        assert!( a.distance( &b) < 1e-9, "{:?} != {:?}", a, b);
     }
 
-    fn render( &self) {
-        self.ctx.clear_rect(0.0, 0.0, self.game_area.width, self.game_area.height);
-        self.shapes.iter().for_each(|shape| shape.borrow().render(&self.ctx));
+    #[test]
+    fn splitting_asteroids_conserves_momentum() {
+        let game = lobby();
+        let mut rng = Random::new( 3);
+        let large = game.objfactory.borrow().create_asteroid_large( Vector::new( 400.0, 300.0), Vector::new( 35.0, -20.0));
+        let medium = game.objfactory.borrow().create_asteroid_medium( Vector::new( 200.0, 100.0), Vector::new( -15.0, 60.0));
+
+        for asteroid in [large, medium] {
+            let mut events = EventQueue::new();
+            asteroid.borrow_mut().collision_with( GameObjectType::Bullet, &mut rng, &mut events);
+
+            let fragments: Vec<_> = events.drain().into_iter().filter_map( |event| match event {
+                GameEvent::SpawnRequested { spawn: Spawn::AsteroidMedium { position, speed } } => Some( game.objfactory.borrow().create_asteroid_medium( position, speed)),
+                GameEvent::SpawnRequested { spawn: Spawn::AsteroidSmall { position, speed } } => Some( game.objfactory.borrow().create_asteroid_small( position, speed)),
+                _ => None,
+            }).collect();
+            let fragments: Vec<_> = fragments.iter().map( |fragment| fragment.borrow()).collect();
+
+            assert_eq!( fragments.len(), 2);
+            assert_close( momentum( &fragments.iter().map( |fragment| &**fragment).collect::<Vec<_>>()), momentum( &[&*asteroid.borrow()]));
+        }
+    }
+
+    #[test]
+    fn elastic_collisions_conserve_momentum() {
+        let game = lobby();
+        let large = game.objfactory.borrow().create_asteroid_large( Vector::new( 380.0, 300.0), Vector::new( 40.0, 10.0));
+        let small = game.objfactory.borrow().create_asteroid_small( Vector::new( 415.0, 305.0), Vector::new( -90.0, 25.0));
+        let before = momentum( &[&*large.borrow(), &*small.borrow()]);
+        let speed_before = small.borrow().velocity();
+
+        // The normal points from the second object to the first
+        let normal = Vector::new( -1.0, -0.2).normalize();
+        collide_elastic( &mut *large.borrow_mut(), &mut *small.borrow_mut(), normal, 5.0);
+
+        assert!( small.borrow().velocity().distance( &speed_before) > 1.0);
+        assert_close( momentum( &[&*large.borrow(), &*small.borrow()]), before);
+    }
+
+    #[test]
+    fn closest_approach_along_the_path() {
+        // Passed right through b: from 50 to the left to 50 to the right
+        assert_eq!( closest_approach( Vector::new( 50.0, 3.0), Vector::new( 100.0, 0.0)), Vector::new( 0.0, 3.0));
+        // Still on the way, or moving away, the ends of the path are closest
+        assert_eq!( closest_approach( Vector::new( -20.0, 3.0), Vector::new( 30.0, 0.0)), Vector::new( -20.0, 3.0));
+        assert_eq!( closest_approach( Vector::new( 80.0, 3.0), Vector::new( 30.0, 0.0)), Vector::new( 50.0, 3.0));
+        assert_eq!( closest_approach( Vector::new( 7.0, 3.0), ZERO), Vector::new( 7.0, 3.0));
+    }
+
+    #[test]
+    fn fast_bullets_do_not_skip_small_asteroids() {
+        let mut game = lobby();
+        let asteroid = game.objfactory.borrow().create_asteroid_small( Vector::new( 400.0, 300.0), ZERO);
+        let bullet = game.objfactory.borrow().create_bullet( Some( 0), None, Vector::new( 370.0, 302.0), Vector::new( 6000.0, 0.0), "red".to_string());
+        game.shapes.insert( asteroid);
+        let id = game.shapes.insert( bullet);
+
+        // 50 px per step, from 30 px before the asteroid to 20 px behind it
+        game.step( &GamepadStates::new());
+
+        assert!( game.shapes.get( id).is_none_or( |bullet| bullet.borrow().is_expired()));
+        assert_eq!( count( &game, GameObjectType::Asteroid), 0);
+    }
+
+    #[test]
+    fn fast_bullets_hit_only_one_asteroid() {
+        let mut game = lobby();
+        let player = game.create_rocket( "red".to_string());
+        let id = game.players[player].unwrap();
+        game.start();
+
+        let first = game.objfactory.borrow().create_asteroid_small( Vector::new( 350.0, 300.0), ZERO);
+        let second = game.objfactory.borrow().create_asteroid_small( Vector::new( 380.0, 300.0), ZERO);
+        let bullet = game.objfactory.borrow().create_bullet( Some( player), None, Vector::new( 320.0, 302.0), Vector::new( 9000.0, 0.0), "red".to_string());
+        game.shapes.insert( first);
+        game.shapes.insert( second);
+        game.shapes.insert( bullet);
+
+        // 75 px per step, through both asteroids
+        game.step( &GamepadStates::new());
+
+        assert_eq!( count( &game, GameObjectType::Asteroid), 1);
+        assert_eq!( count( &game, GameObjectType::Bullet), 0);
+        assert_eq!( game.shapes.with_mut( id, |rocket: &mut Rocket| rocket.score), Some( 100));
+    }
+
+    #[test]
+    fn practice_in_the_lobby_does_not_count() {
+        let mut game = lobby();
+        let player = game.create_rocket( "red".to_string());
+        let id = game.players[player].unwrap();
+        let position = Vector::new( 600.0, 500.0);
+        let asteroid = game.objfactory.borrow().create_asteroid_small( position, ZERO);
+        let bullet = game.objfactory.borrow().create_bullet( Some( player), None, position, ZERO, "red".to_string());
+        game.shapes.insert( asteroid);
+        game.shapes.insert( bullet);
+
+        game.step( &GamepadStates::new());
+
+        assert_eq!( count( &game, GameObjectType::Asteroid), 0);
+        assert_eq!( game.shapes.with_mut( id, |rocket: &mut Rocket| rocket.score), Some( 0));
+
+        game.shapes.with_mut( id, |rocket: &mut Rocket| {
+            rocket.score = 500;
+            rocket.damage = 100;
+        });
+        game.start();
+
+        assert_eq!( game.state(), GameState::Countdown);
+        assert_eq!( game.shapes.with_mut( id, |rocket: &mut Rocket| (rocket.score, rocket.damage)), Some( (0, 0)));
+    }
+
+    #[test]
+    fn lives_lost_in_the_lobby_are_back_for_the_match() {
+        let mut game = Game::headless( 800.0, 600.0, Box::new( NullRenderer), 1).with_mode( GameModeKind::LastRocketStanding);
+        game.start();
+        let player = game.create_rocket( "red".to_string());
+        let id = game.players[player].unwrap();
+        let lives = game.mode.lives();
+        assert!( lives.is_some());
+
+        game.shapes.with_mut( id, |rocket: &mut Rocket| {
+            rocket.lives = Some( 0);
+            rocket.destroyed = true;
+        });
+        assert_eq!( game.shapes.with_mut( id, |rocket: &mut Rocket| rocket.is_out_of_lives()), Some( true));
+
+        game.start();
+
+        assert_eq!( game.shapes.with_mut( id, |rocket: &mut Rocket| (rocket.lives, rocket.is_destroyed())), Some( (lives, false)));
     }
 }
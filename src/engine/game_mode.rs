@@ -0,0 +1,352 @@
+use serde::{Serialize, Deserialize};
+use wasm_bindgen::prelude::*;
+use crate::engine::{GameObjectType, Wave, Waves, SizeMix};
+use crate::components::START_LIVES;
+
+// Seconds between clearing a round and the countdown of the next one
+const ROUND_OVER_TIME: f64 = 2.0;
+// Score a deathmatch is played to
+const DEATHMATCH_SCORE: i32 = 10000;
+// Seconds of shooting in a time attack, countdowns and breaks between rounds do not count
+const TIME_ATTACK_TIME: f64 = 180.0;
+
+// Game modes that can be chosen when creating a game
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameModeKind {
+    // Everybody against the asteroids, round after round until all lives are lost
+    #[default]
+    CoopSurvival,
+    // Rockets hunt each other, the first to reach the score wins
+    Deathmatch,
+    // Highest score when the time is up wins
+    TimeAttack,
+    // Rockets have a few lives, the last one flying wins
+    LastRocketStanding,
+}
+
+impl GameModeKind {
+    pub fn create( &self) -> Box<dyn GameMode> {
+        match self {
+            GameModeKind::CoopSurvival => Box::new( CoopSurvival),
+            GameModeKind::Deathmatch => Box::new( Deathmatch),
+            GameModeKind::TimeAttack => Box::new( TimeAttack),
+            GameModeKind::LastRocketStanding => Box::new( LastRocketStanding),
+        }
+    }
+}
+
+// How a finished match ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchOutcome {
+    // Every rocket is out of lives
+    Defeated,
+    Winner { player: usize },
+    WinningTeam { team: usize },
+    Draw,
+}
+
+// A team, or a rocket playing for itself
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Player( usize),
+    Team( usize),
+}
+
+pub struct RocketInfo {
+    pub player: usize,
+    pub team: Option<usize>,
+    pub score: i32,
+    pub out_of_lives: bool,
+}
+
+// What a game mode gets to see of the match
+pub struct MatchInfo {
+    pub round: i32,
+    // Seconds played, see TIME_ATTACK_TIME
+    pub time: f64,
+    pub rockets: Vec<RocketInfo>,
+}
+
+impl MatchInfo {
+    // Score total and whether any of its rockets is still in the game, for every side
+    fn sides( &self) -> Vec<(Side, i32, bool)> {
+        let mut sides: Vec<(Side, i32, bool)> = vec![];
+
+        for rocket in &self.rockets {
+            let side = match rocket.team {
+                Some( team) => Side::Team( team),
+                None => Side::Player( rocket.player),
+            };
+
+            match sides.iter_mut().find( |(other, _, _)| *other == side) {
+                Some( (_, score, alive)) => {
+                    *score += rocket.score;
+                    *alive |= !rocket.out_of_lives;
+                }
+                None => sides.push( (side, rocket.score, !rocket.out_of_lives)),
+            }
+        }
+
+        return sides;
+    }
+
+    fn all_out_of_lives( &self) -> bool {
+        !self.rockets.is_empty() && self.rockets.iter().all( |rocket| rocket.out_of_lives)
+    }
+
+    // Side with the highest score, a draw when several share it
+    fn leader( &self) -> MatchOutcome {
+        let sides = self.sides();
+        let Some( best) = sides.iter().map( |(_, score, _)| *score).max() else {
+            return MatchOutcome::Defeated;
+        };
+
+        let mut leaders = sides.iter().filter( |(_, score, _)| *score == best);
+        match (leaders.next(), leaders.next()) {
+            (Some( (side, _, _)), None) => winner( *side),
+            _ => MatchOutcome::Draw,
+        }
+    }
+}
+
+fn winner( side: Side) -> MatchOutcome {
+    match side {
+        Side::Player( player) => MatchOutcome::Winner { player },
+        Side::Team( team) => MatchOutcome::WinningTeam { team },
+    }
+}
+
+// Rules of a match: what spawns, what scores, when rounds end and who wins
+pub trait GameMode {
+    fn kind( &self) -> GameModeKind;
+
+    // Points for the player whose bullet hit an object of this type
+    fn points( &self, target: GameObjectType) -> i32;
+
+    // Checked after every step of a match, Some ends it
+    fn outcome( &self, info: &MatchInfo) -> Option<MatchOutcome>;
+
+    // Lives every rocket starts with, None respawns rockets forever
+    fn lives( &self) -> Option<i32> {
+        Some( START_LIVES)
+    }
+
+    // Asteroids, saucers and power-ups of a round, usually from the loaded waves
+    fn wave( &self, waves: &Waves, round: i32) -> Wave {
+        waves.wave( round)
+    }
+
+    fn round_cleared( &self, asteroids_left: usize) -> bool {
+        asteroids_left == 0
+    }
+
+    // Seconds between clearing a round and the countdown of the next one
+    fn round_over_time( &self) -> f64 {
+        ROUND_OVER_TIME
+    }
+
+    // Line shown above the match, e.g. the time left
+    fn status( &self, _info: &MatchInfo) -> Option<String> {
+        None
+    }
+}
+
+pub struct CoopSurvival;
+
+impl GameMode for CoopSurvival {
+    fn kind( &self) -> GameModeKind {
+        GameModeKind::CoopSurvival
+    }
+
+    fn points( &self, target: GameObjectType) -> i32 {
+        match target {
+            GameObjectType::Asteroid => 100,
+            GameObjectType::Saucer => 500,
+            _ => 0,
+        }
+    }
+
+    fn outcome( &self, info: &MatchInfo) -> Option<MatchOutcome> {
+        info.all_out_of_lives().then_some( MatchOutcome::Defeated)
+    }
+}
+
+pub struct Deathmatch;
+
+impl GameMode for Deathmatch {
+    fn kind( &self) -> GameModeKind {
+        GameModeKind::Deathmatch
+    }
+
+    fn points( &self, target: GameObjectType) -> i32 {
+        match target {
+            GameObjectType::Rocket => 100,
+            GameObjectType::Asteroid => 10,
+            _ => 0,
+        }
+    }
+
+    fn outcome( &self, info: &MatchInfo) -> Option<MatchOutcome> {
+        info.sides().iter()
+            .find( |(_, score, _)| *score >= DEATHMATCH_SCORE)
+            .map( |(side, _, _)| winner( *side))
+    }
+
+    fn lives( &self) -> Option<i32> {
+        None
+    }
+
+    // A few slow asteroids as obstacles, no saucers
+    fn wave( &self, _waves: &Waves, round: i32) -> Wave {
+        Wave {
+            asteroids: 3 + round,
+            sizes: SizeMix { large: 1.0, medium: 0.0, small: 0.0 },
+            min_speed: 0.0,
+            max_speed: 40.0,
+            edges: vec![],
+            saucer_interval: None,
+            small_saucer_chance: 0.0,
+            power_up_chance: 0.08,
+        }
+    }
+
+    fn status( &self, _info: &MatchInfo) -> Option<String> {
+        Some( format!( "First to {}", DEATHMATCH_SCORE))
+    }
+}
+
+pub struct TimeAttack;
+
+impl GameMode for TimeAttack {
+    fn kind( &self) -> GameModeKind {
+        GameModeKind::TimeAttack
+    }
+
+    fn points( &self, target: GameObjectType) -> i32 {
+        match target {
+            GameObjectType::Asteroid => 100,
+            GameObjectType::Saucer => 500,
+            _ => 0,
+        }
+    }
+
+    fn outcome( &self, info: &MatchInfo) -> Option<MatchOutcome> {
+        (info.time >= TIME_ATTACK_TIME).then( || info.leader())
+    }
+
+    // Losing a rocket only costs time
+    fn lives( &self) -> Option<i32> {
+        None
+    }
+
+    fn round_over_time( &self) -> f64 {
+        ROUND_OVER_TIME / 4.0
+    }
+
+    fn status( &self, info: &MatchInfo) -> Option<String> {
+        let left = (TIME_ATTACK_TIME - info.time).max( 0.0).ceil() as i32;
+
+        Some( format!( "Time left {}:{:02}", left / 60, left % 60))
+    }
+}
+
+pub struct LastRocketStanding;
+
+impl GameMode for LastRocketStanding {
+    fn kind( &self) -> GameModeKind {
+        GameModeKind::LastRocketStanding
+    }
+
+    fn points( &self, target: GameObjectType) -> i32 {
+        match target {
+            GameObjectType::Rocket => 100,
+            GameObjectType::Asteroid => 10,
+            GameObjectType::Saucer => 100,
+            _ => 0,
+        }
+    }
+
+    fn outcome( &self, info: &MatchInfo) -> Option<MatchOutcome> {
+        let sides = info.sides();
+
+        // Alone there is nobody to outlast, it ends like a survival match
+        if sides.len() < 2 {
+            return info.all_out_of_lives().then_some( MatchOutcome::Defeated);
+        }
+
+        let mut alive = sides.iter().filter( |(_, _, alive)| *alive);
+        match (alive.next(), alive.next()) {
+            (Some( (side, _, _)), None) => Some( winner( *side)),
+            (None, _) => Some( MatchOutcome::Draw),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rocket( player: usize, team: Option<usize>, score: i32, out_of_lives: bool) -> RocketInfo {
+        RocketInfo { player, team, score, out_of_lives }
+    }
+
+    fn info( time: f64, rockets: Vec<RocketInfo>) -> MatchInfo {
+        MatchInfo { round: 1, time, rockets }
+    }
+
+    #[test]
+    fn leader() {
+        assert_eq!( info( 0.0, vec![rocket( 0, None, 300, false), rocket( 1, None, 500, false)]).leader(), MatchOutcome::Winner { player: 1 });
+        assert_eq!( info( 0.0, vec![rocket( 0, None, 500, false), rocket( 1, None, 500, false)]).leader(), MatchOutcome::Draw);
+
+        // Team scores add up
+        let teams = info( 0.0, vec![rocket( 0, Some( 0), 400, false), rocket( 1, Some( 1), 500, false), rocket( 2, Some( 0), 200, false)]);
+        assert_eq!( teams.leader(), MatchOutcome::WinningTeam { team: 0 });
+
+        let tied = info( 0.0, vec![rocket( 0, Some( 0), 200, false), rocket( 1, Some( 1), 500, false), rocket( 2, Some( 0), 300, false)]);
+        assert_eq!( tied.leader(), MatchOutcome::Draw);
+    }
+
+    #[test]
+    fn last_rocket_standing() {
+        let mode = LastRocketStanding;
+
+        assert_eq!( mode.outcome( &info( 0.0, vec![rocket( 0, None, 0, false), rocket( 1, None, 0, false)])), None);
+        assert_eq!( mode.outcome( &info( 0.0, vec![rocket( 0, None, 0, true), rocket( 1, None, 0, false)])), Some( MatchOutcome::Winner { player: 1 }));
+        assert_eq!( mode.outcome( &info( 0.0, vec![rocket( 0, None, 0, true), rocket( 1, None, 0, true)])), Some( MatchOutcome::Draw));
+
+        // A team is still standing while one of its rockets is
+        let teams = vec![rocket( 0, Some( 0), 0, true), rocket( 1, Some( 1), 0, true), rocket( 2, Some( 0), 0, false)];
+        assert_eq!( mode.outcome( &info( 0.0, teams)), Some( MatchOutcome::WinningTeam { team: 0 }));
+
+        // Fewer than two sides play until everybody is out
+        assert_eq!( mode.outcome( &info( 0.0, vec![])), None);
+        assert_eq!( mode.outcome( &info( 0.0, vec![rocket( 0, None, 0, false)])), None);
+        assert_eq!( mode.outcome( &info( 0.0, vec![rocket( 0, None, 0, true)])), Some( MatchOutcome::Defeated));
+        assert_eq!( mode.outcome( &info( 0.0, vec![rocket( 0, Some( 1), 0, false), rocket( 1, Some( 1), 0, true)])), None);
+    }
+
+    #[test]
+    fn deathmatch_ends_at_the_target_score() {
+        let mode = Deathmatch;
+
+        assert_eq!( mode.outcome( &info( 0.0, vec![rocket( 0, None, DEATHMATCH_SCORE - 1, false), rocket( 1, None, 0, false)])), None);
+        assert_eq!( mode.outcome( &info( 0.0, vec![rocket( 0, None, 0, false), rocket( 1, None, DEATHMATCH_SCORE, false)])), Some( MatchOutcome::Winner { player: 1 }));
+
+        let teams = vec![rocket( 0, Some( 1), DEATHMATCH_SCORE / 2, false), rocket( 1, Some( 0), 0, false), rocket( 2, Some( 1), DEATHMATCH_SCORE / 2, false)];
+        assert_eq!( mode.outcome( &info( 0.0, teams)), Some( MatchOutcome::WinningTeam { team: 1 }));
+    }
+
+    #[test]
+    fn time_attack_ends_when_the_time_is_up() {
+        let mode = TimeAttack;
+        let rockets = || vec![rocket( 0, None, 100, true), rocket( 1, None, 200, false)];
+
+        assert_eq!( mode.outcome( &info( TIME_ATTACK_TIME - 0.01, rockets())), None);
+        assert_eq!( mode.outcome( &info( TIME_ATTACK_TIME, rockets())), Some( MatchOutcome::Winner { player: 1 }));
+        assert_eq!( mode.outcome( &info( TIME_ATTACK_TIME, vec![rocket( 0, None, 100, false), rocket( 1, None, 100, false)])), Some( MatchOutcome::Draw));
+        assert_eq!( mode.status( &info( TIME_ATTACK_TIME - 61.5, rockets())), Some( "Time left 1:02".to_string()));
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Serialize, Deserialize};
+use crate::utils::Vector;
+
+// Convex collision outline of an object, in pixels around its center at rotation 0.
+// Must fit into the circle of GameObject::radius, which is tested first.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hull {
+    pub points: Vec<Vector>,
+}
+
+impl Hull {
+    pub fn new( points: Vec<Vector>) -> Self {
+        Hull { points }
+    }
+
+    // Points around the center of the object, rotated like its sprite
+    pub fn rotated( &self, rotation: f64) -> Vec<Vector> {
+        self.points.iter().map( |point| point.rotate( rotation)).collect()
+    }
+}
+
+// Normals of the edges, the axes along which two convex polygons can be separated
+fn axes( polygon: &[Vector]) -> impl Iterator<Item = Vector> + '_ {
+    (0..polygon.len()).map( move |i| {
+        let edge = polygon[(i + 1) % polygon.len()].sub( &polygon[i]);
+        Vector::new( -edge.y, edge.x).normalize()
+    })
+}
+
+fn project( polygon: &[Vector], axis: &Vector) -> (f64, f64) {
+    polygon.iter()
+        .map( |point| point.dot( axis))
+        .fold( (f64::MAX, f64::MIN), |(min, max), distance| (min.min( distance), max.max( distance)))
+}
+
+// Overlap of two projections, negative when there is a gap
+fn overlap( a: (f64, f64), b: (f64, f64)) -> f64 {
+    a.1.min( b.1) - a.0.max( b.0)
+}
+
+// Separating axis theorem: two convex polygons overlap unless one of their edge normals separates them.
+// Returns how deep they overlap along the axis where they overlap least, None when they do not touch.
+pub fn polygons_overlap( a: &[Vector], b: &[Vector]) -> Option<f64> {
+    axes( a).chain( axes( b))
+        .map( |axis| overlap( project( a, &axis), project( b, &axis)))
+        .try_fold( f64::MAX, |depth, overlap| (overlap >= 0.0).then_some( depth.min( overlap)))
+}
+
+// Same for a circle, which adds the axis from the nearest corner of the polygon to its center
+pub fn polygon_overlaps_circle( polygon: &[Vector], center: Vector, radius: f64) -> Option<f64> {
+    let nearest = polygon.iter().min_by( |a, b| a.distance( &center).total_cmp( &b.distance( &center)))?;
+
+    let corner_axis = center.sub( nearest).normalize();
+    let circle = |axis: &Vector| {
+        let distance = center.dot( axis);
+        (distance - radius, distance + radius)
+    };
+
+    axes( polygon).chain( std::iter::once( corner_axis))
+        .map( |axis| overlap( project( polygon, &axis), circle( &axis)))
+        .try_fold( f64::MAX, |depth, overlap| (overlap >= 0.0).then_some( depth.min( overlap)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Square with sides of 10 at position, turned by rotation
+    fn square( position: Vector, rotation: f64) -> Vec<Vector> {
+        let hull = Hull::new( vec![Vector::new( -5.0, -5.0), Vector::new( 5.0, -5.0), Vector::new( 5.0, 5.0), Vector::new( -5.0, 5.0)]);
+        hull.rotated( rotation).iter().map( |point| point.add( &position)).collect()
+    }
+
+    fn assert_depth( depth: Option<f64>, expected: f64) {
+        let depth = depth.expect( "should overlap");
+        assert!( (depth - expected).abs() < 1e-9, "{} != {}", depth, expected);
+    }
+
+    #[test]
+    fn polygons() {
+        let origin = square( Vector::new( 0.0, 0.0), 0.0);
+
+        assert_depth( polygons_overlap( &origin, &square( Vector::new( 8.0, 3.0), 0.0)), 2.0);
+        assert_depth( polygons_overlap( &origin, &square( Vector::new( 10.0, 0.0), 0.0)), 0.0);
+        assert_eq!( polygons_overlap( &origin, &square( Vector::new( 10.5, 0.0), 0.0)), None);
+        assert_eq!( polygons_overlap( &origin, &square( Vector::new( 8.0, 12.0), 0.0)), None);
+
+        // Turned by 45°, a corner reaches 5 * √2 towards the other square
+        let diamond = |x| square( Vector::new( x, 0.0), std::f64::consts::FRAC_PI_4);
+        assert_depth( polygons_overlap( &origin, &diamond( 11.0)), 5.0 + 50f64.sqrt() - 11.0);
+        assert_eq!( polygons_overlap( &origin, &diamond( 12.5)), None);
+        assert_eq!( polygons_overlap( &diamond( 12.5), &origin), None);
+    }
+
+    #[test]
+    fn polygons_and_circles() {
+        let origin = square( Vector::new( 0.0, 0.0), 0.0);
+
+        assert_depth( polygon_overlaps_circle( &origin, Vector::new( 9.0, 0.0), 5.0), 1.0);
+        assert_depth( polygon_overlaps_circle( &origin, Vector::new( 0.0, -10.0), 5.0), 0.0);
+        assert_eq!( polygon_overlaps_circle( &origin, Vector::new( 0.0, -10.5), 5.0), None);
+
+        // Within reach of both edges next to the corner, but not of the corner itself
+        assert_eq!( polygon_overlaps_circle( &origin, Vector::new( 9.0, 9.0), 5.0), None);
+        assert_depth( polygon_overlaps_circle( &origin, Vector::new( 8.0, 8.0), 5.0), 5.0 - 18f64.sqrt());
+        assert_eq!( polygon_overlaps_circle( &[], Vector::new( 0.0, 0.0), 5.0), None);
+    }
+}
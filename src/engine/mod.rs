@@ -1,7 +1,71 @@
 mod game;
+mod renderer;
+mod canvas_renderer;
+mod entities;
+mod events;
+mod broad_phase;
+mod state;
+mod snapshot;
+mod replay;
+mod bot;
+mod teams;
+mod game_mode;
+mod waves;
+mod hull;
+pub mod collision;
 
 pub use game::GameObject;
 pub use game::GameObjectType;
 pub use game::GamepadState;
+pub use game::GamepadStates;
+pub use game::ResetOptions;
 pub use game::Area;
-pub use game::Game;
\ No newline at end of file
+pub use game::Game;
+pub use state::GameState;
+pub use snapshot::ObjectSnapshot;
+pub use snapshot::WorldSnapshot;
+pub use snapshot::SnapshotError;
+pub use snapshot::SnapshotDelta;
+pub use snapshot::SNAPSHOT_VERSION;
+pub use replay::Replay;
+pub use replay::ReplayFrame;
+pub use replay::ReplayCommand;
+pub use replay::REPLAY_VERSION;
+pub use bot::Bot;
+pub use bot::BotDifficulty;
+pub use teams::FriendlyFire;
+pub use teams::TEAM_COLORS;
+pub use teams::team_color;
+pub use game_mode::GameMode;
+pub use game_mode::GameModeKind;
+pub use game_mode::MatchInfo;
+pub use game_mode::MatchOutcome;
+pub use game_mode::RocketInfo;
+pub use waves::Wave;
+pub use waves::Waves;
+pub use waves::WavesError;
+pub use waves::Edge;
+pub use waves::SizeMix;
+pub use waves::Endless;
+pub use hull::Hull;
+pub use hull::polygons_overlap;
+pub use hull::polygon_overlaps_circle;
+pub use game::FIXED_DELTA_T;
+pub use game::interpolate;
+pub use renderer::Renderer;
+pub use renderer::Sprite;
+pub use renderer::NullRenderer;
+pub use renderer::RecordingRenderer;
+pub use renderer::DrawCommand;
+pub use canvas_renderer::CanvasRenderer;
+pub use entities::Entities;
+pub use entities::EntityId;
+pub use events::EventQueue;
+pub use events::EventListener;
+pub use events::GameEvent;
+pub use events::Spawn;
+pub use broad_phase::SpatialHash;
+pub use collision::CollisionMatrix;
+pub use collision::CollisionResponse;
+pub use collision::CollisionRule;
+pub use collision::CollisionsError;
@@ -0,0 +1,126 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sprite {
+    pub id: usize,
+    pub width: f64,
+    pub height: f64,
+}
+
+pub trait Renderer {
+    fn clear( &mut self, width: f64, height: f64);
+
+    fn save( &mut self);
+    fn restore( &mut self);
+    fn translate( &mut self, x: f64, y: f64);
+    fn rotate( &mut self, angle: f64);
+    fn scale( &mut self, x: f64, y: f64);
+    fn set_alpha( &mut self, alpha: f64);
+
+    // Draws the sprite centered around the given point
+    fn draw_sprite( &mut self, sprite: &Sprite, x: f64, y: f64);
+    fn fill_circle( &mut self, x: f64, y: f64, radius: f64, color: &str);
+    fn stroke_circle( &mut self, x: f64, y: f64, radius: f64, line_width: f64, color: &str);
+    fn fill_rect( &mut self, x: f64, y: f64, width: f64, height: f64, color: &str);
+    fn fill_text( &mut self, text: &str, x: f64, y: f64, font: &str, color: &str);
+}
+
+// Renderer for native builds that throws everything away
+pub struct NullRenderer;
+
+impl Renderer for NullRenderer {
+    fn clear( &mut self, _width: f64, _height: f64) {}
+    fn save( &mut self) {}
+    fn restore( &mut self) {}
+    fn translate( &mut self, _x: f64, _y: f64) {}
+    fn rotate( &mut self, _angle: f64) {}
+    fn scale( &mut self, _x: f64, _y: f64) {}
+    fn set_alpha( &mut self, _alpha: f64) {}
+    fn draw_sprite( &mut self, _sprite: &Sprite, _x: f64, _y: f64) {}
+    fn fill_circle( &mut self, _x: f64, _y: f64, _radius: f64, _color: &str) {}
+    fn stroke_circle( &mut self, _x: f64, _y: f64, _radius: f64, _line_width: f64, _color: &str) {}
+    fn fill_rect( &mut self, _x: f64, _y: f64, _width: f64, _height: f64, _color: &str) {}
+    fn fill_text( &mut self, _text: &str, _x: f64, _y: f64, _font: &str, _color: &str) {}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    Clear { width: f64, height: f64 },
+    Save,
+    Restore,
+    Translate { x: f64, y: f64 },
+    Rotate { angle: f64 },
+    Scale { x: f64, y: f64 },
+    SetAlpha { alpha: f64 },
+    Sprite { sprite: Sprite, x: f64, y: f64 },
+    FillCircle { x: f64, y: f64, radius: f64, color: String },
+    StrokeCircle { x: f64, y: f64, radius: f64, line_width: f64, color: String },
+    FillRect { x: f64, y: f64, width: f64, height: f64, color: String },
+    FillText { text: String, x: f64, y: f64, font: String, color: String },
+}
+
+// Renderer for native builds that keeps a list of all draw calls, so they can be inspected afterwards
+#[derive(Default)]
+pub struct RecordingRenderer {
+    pub commands: Vec<DrawCommand>,
+}
+
+impl RecordingRenderer {
+    pub fn new() -> Self {
+        RecordingRenderer { commands: vec![] }
+    }
+
+    pub fn take( &mut self) -> Vec<DrawCommand> {
+        std::mem::take( &mut self.commands)
+    }
+}
+
+impl Renderer for RecordingRenderer {
+    fn clear( &mut self, width: f64, height: f64) {
+        self.commands.push( DrawCommand::Clear { width, height });
+    }
+
+    fn save( &mut self) {
+        self.commands.push( DrawCommand::Save);
+    }
+
+    fn restore( &mut self) {
+        self.commands.push( DrawCommand::Restore);
+    }
+
+    fn translate( &mut self, x: f64, y: f64) {
+        self.commands.push( DrawCommand::Translate { x, y });
+    }
+
+    fn rotate( &mut self, angle: f64) {
+        self.commands.push( DrawCommand::Rotate { angle });
+    }
+
+    fn scale( &mut self, x: f64, y: f64) {
+        self.commands.push( DrawCommand::Scale { x, y });
+    }
+
+    fn set_alpha( &mut self, alpha: f64) {
+        self.commands.push( DrawCommand::SetAlpha { alpha });
+    }
+
+    fn draw_sprite( &mut self, sprite: &Sprite, x: f64, y: f64) {
+        self.commands.push( DrawCommand::Sprite { sprite: *sprite, x, y });
+    }
+
+    fn fill_circle( &mut self, x: f64, y: f64, radius: f64, color: &str) {
+        self.commands.push( DrawCommand::FillCircle { x, y, radius, color: color.to_string() });
+    }
+
+    fn stroke_circle( &mut self, x: f64, y: f64, radius: f64, line_width: f64, color: &str) {
+        self.commands.push( DrawCommand::StrokeCircle { x, y, radius, line_width, color: color.to_string() });
+    }
+
+    fn fill_rect( &mut self, x: f64, y: f64, width: f64, height: f64, color: &str) {
+        self.commands.push( DrawCommand::FillRect { x, y, width, height, color: color.to_string() });
+    }
+
+    fn fill_text( &mut self, text: &str, x: f64, y: f64, font: &str, color: &str) {
+        self.commands.push( DrawCommand::FillText { text: text.to_string(), x, y, font: font.to_string(), color: color.to_string() });
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Serialize, Deserialize};
+use crate::engine::{GamepadState, WorldSnapshot, SnapshotError, BotDifficulty, FriendlyFire, Waves, CollisionMatrix};
+
+// Increase whenever the layout of a replay changes, old replays are rejected then
+pub const REPLAY_VERSION: u32 = 5;
+
+// Changes to the game that do not come from the gamepads, e.g. key presses or players joining
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReplayCommand {
+    Start,
+    Pause,
+    Resume,
+    EndGame,
+    Reset { keep_rockets: bool },
+    CreateRocket { color: String },
+    RemoveRocket { rocket_index: usize },
+    AddBot { difficulty: BotDifficulty },
+    SetTeam { rocket_index: usize, team: Option<usize> },
+    SetFriendlyFire { friendly_fire: FriendlyFire },
+    SetWaves { waves: Waves },
+    SetCollisions { collision_matrix: CollisionMatrix },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub frame_time: f64,
+    pub states: Vec<GamepadState>,
+    // Issued before the frame, applied before its simulation steps
+    pub commands: Vec<ReplayCommand>,
+}
+
+// A recorded match: the world at the start of the recording plus the input of every frame after it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u32,
+    pub start: WorldSnapshot,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new( start: WorldSnapshot) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            seed: start.seed,
+            start,
+            frames: vec![],
+        }
+    }
+
+    pub fn to_json( &self) -> String {
+        serde_json::to_string( self).expect( "replay is always serializable")
+    }
+
+    pub fn from_json( json: &str) -> Result<Replay, SnapshotError> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+
+        let version: Version = serde_json::from_str( json).map_err( |e| SnapshotError::Invalid( e.to_string()))?;
+        if version.version != REPLAY_VERSION {
+            return Err( SnapshotError::UnsupportedVersion( version.version));
+        }
+
+        serde_json::from_str( json).map_err( |e| SnapshotError::Invalid( e.to_string()))
+    }
+}
+
+pub struct ReplayRecorder {
+    replay: Replay,
+    pending_commands: Vec<ReplayCommand>,
+}
+
+impl ReplayRecorder {
+    pub fn new( start: WorldSnapshot) -> Self {
+        ReplayRecorder {
+            replay: Replay::new( start),
+            pending_commands: vec![],
+        }
+    }
+
+    pub fn record_command( &mut self, command: ReplayCommand) {
+        self.pending_commands.push( command);
+    }
+
+    pub fn record_frame( &mut self, frame_time: f64, states: Vec<GamepadState>) {
+        self.replay.frames.push( ReplayFrame {
+            frame_time,
+            states,
+            commands: std::mem::take( &mut self.pending_commands),
+        });
+    }
+
+    pub fn finish( self) -> Replay {
+        self.replay
+    }
+}
+
+pub struct ReplayPlayer {
+    replay: Replay,
+    position: usize,
+    // Seconds that passed while playing, but are not yet covered by played frames
+    time: f64,
+}
+
+impl ReplayPlayer {
+    pub fn new( replay: Replay) -> Self {
+        ReplayPlayer { replay, position: 0, time: 0.0 }
+    }
+
+    // Recorded frames whose time has come after another frame_time seconds, so a replay runs at the pace it was
+    // recorded at, no matter how often the display refreshes
+    pub fn due_frames( &mut self, frame_time: f64) -> Vec<ReplayFrame> {
+        self.time += frame_time;
+
+        let mut frames = vec![];
+        while let Some( frame) = self.replay.frames.get( self.position) && frame.frame_time <= self.time {
+            self.time -= frame.frame_time;
+            self.position += 1;
+            frames.push( frame.clone());
+        }
+
+        frames
+    }
+
+    pub fn is_finished( &self) -> bool {
+        self.position >= self.replay.frames.len()
+    }
+}
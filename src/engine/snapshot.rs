@@ -0,0 +1,176 @@
+use std::fmt;
+use std::rc::Rc;
+use std::cell::RefCell;
+use serde::{Serialize, Deserialize};
+use crate::utils::Random;
+use crate::engine::{GameObject, GameState, EntityId, Bot, FriendlyFire, GameModeKind, MatchOutcome, Waves, CollisionMatrix};
+use crate::engine::entities::{EntitiesSnapshot, EntitiesDelta};
+use crate::components::{Asteroid, Bullet, Countdown, Explosion, PowerUp, Rocket, Saucer};
+
+// Increase whenever the layout of a snapshot changes, old snapshots are rejected then
+pub const SNAPSHOT_VERSION: u32 = 10;
+
+// Complete state of a single game object
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ObjectSnapshot {
+    Asteroid( Asteroid),
+    Rocket( Rocket),
+    Bullet( Bullet),
+    Explosion( Explosion),
+    Countdown( Countdown),
+    PowerUp( PowerUp),
+    Saucer( Saucer),
+}
+
+impl ObjectSnapshot {
+    pub fn into_object( self) -> Rc<RefCell<dyn GameObject>> {
+        match self {
+            ObjectSnapshot::Asteroid( asteroid) => Rc::new( RefCell::new( asteroid)),
+            ObjectSnapshot::Rocket( rocket) => Rc::new( RefCell::new( rocket)),
+            ObjectSnapshot::Bullet( bullet) => Rc::new( RefCell::new( bullet)),
+            ObjectSnapshot::Explosion( explosion) => Rc::new( RefCell::new( explosion)),
+            ObjectSnapshot::Countdown( countdown) => Rc::new( RefCell::new( countdown)),
+            ObjectSnapshot::PowerUp( power_up) => Rc::new( RefCell::new( power_up)),
+            ObjectSnapshot::Saucer( saucer) => Rc::new( RefCell::new( saucer)),
+        }
+    }
+}
+
+// Everything needed to continue a match exactly where it was left
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub version: u32,
+    pub state: GameState,
+    pub paused_state: GameState,
+    pub state_time: f64,
+    pub round: i32,
+    pub seed: u32,
+    pub rng: Random,
+    pub accumulator: f64,
+    pub saucer_time: f64,
+    pub players: Vec<Option<EntityId>>,
+    pub bots: Vec<Bot>,
+    pub friendly_fire: FriendlyFire,
+    pub mode: GameModeKind,
+    pub waves: Waves,
+    pub collision_matrix: CollisionMatrix,
+    pub match_time: f64,
+    pub outcome: Option<MatchOutcome>,
+    pub entities: EntitiesSnapshot,
+}
+
+impl WorldSnapshot {
+    pub fn to_json( &self) -> String {
+        serde_json::to_string( self).expect( "world snapshot is always serializable")
+    }
+
+    pub fn from_json( json: &str) -> Result<WorldSnapshot, SnapshotError> {
+        // Check the version first, the rest of an old snapshot may not parse at all
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+
+        let version: Version = serde_json::from_str( json).map_err( |e| SnapshotError::Invalid( e.to_string()))?;
+        if version.version != SNAPSHOT_VERSION {
+            return Err( SnapshotError::UnsupportedVersion( version.version));
+        }
+
+        serde_json::from_str( json).map_err( |e| SnapshotError::Invalid( e.to_string()))
+    }
+}
+
+// Difference between two world snapshots, much smaller than a complete snapshot as long as most objects did not change
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SnapshotDelta {
+    pub version: u32,
+    pub state: GameState,
+    pub paused_state: GameState,
+    pub state_time: f64,
+    pub round: i32,
+    pub seed: u32,
+    pub rng: Random,
+    pub accumulator: f64,
+    pub saucer_time: f64,
+    pub players: Vec<Option<EntityId>>,
+    pub bots: Vec<Bot>,
+    pub friendly_fire: FriendlyFire,
+    pub mode: GameModeKind,
+    // None when the same as in the base, waves and collisions rarely change
+    pub waves: Option<Waves>,
+    pub collision_matrix: Option<CollisionMatrix>,
+    pub match_time: f64,
+    pub outcome: Option<MatchOutcome>,
+    pub entities: EntitiesDelta,
+}
+
+impl WorldSnapshot {
+    // Only the objects that differ from base, see SnapshotDelta::apply
+    pub fn delta( &self, base: &WorldSnapshot) -> SnapshotDelta {
+        SnapshotDelta {
+            version: self.version,
+            state: self.state,
+            paused_state: self.paused_state,
+            state_time: self.state_time,
+            round: self.round,
+            seed: self.seed,
+            rng: self.rng,
+            accumulator: self.accumulator,
+            saucer_time: self.saucer_time,
+            players: self.players.clone(),
+            bots: self.bots.clone(),
+            friendly_fire: self.friendly_fire,
+            mode: self.mode,
+            waves: (self.waves != base.waves).then( || self.waves.clone()),
+            collision_matrix: (self.collision_matrix != base.collision_matrix).then( || self.collision_matrix.clone()),
+            match_time: self.match_time,
+            outcome: self.outcome,
+            entities: self.entities.delta( &base.entities),
+        }
+    }
+}
+
+impl SnapshotDelta {
+    // The snapshot this delta was made from, given the same base
+    pub fn apply( &self, base: &WorldSnapshot) -> Result<WorldSnapshot, SnapshotError> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err( SnapshotError::UnsupportedVersion( self.version));
+        }
+
+        Ok( WorldSnapshot {
+            version: self.version,
+            state: self.state,
+            paused_state: self.paused_state,
+            state_time: self.state_time,
+            round: self.round,
+            seed: self.seed,
+            rng: self.rng,
+            accumulator: self.accumulator,
+            saucer_time: self.saucer_time,
+            players: self.players.clone(),
+            bots: self.bots.clone(),
+            friendly_fire: self.friendly_fire,
+            mode: self.mode,
+            waves: self.waves.clone().unwrap_or_else( || base.waves.clone()),
+            collision_matrix: self.collision_matrix.clone().unwrap_or_else( || base.collision_matrix.clone()),
+            match_time: self.match_time,
+            outcome: self.outcome,
+            entities: self.entities.apply( &base.entities)?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SnapshotError {
+    UnsupportedVersion( u32),
+    Invalid( String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt( &self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::UnsupportedVersion( version) => write!( f, "unsupported snapshot version {} (expected {})", version, SNAPSHOT_VERSION),
+            SnapshotError::Invalid( reason) => write!( f, "invalid snapshot: {}", reason),
+        }
+    }
+}
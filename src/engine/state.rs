@@ -0,0 +1,18 @@
+use wasm_bindgen::prelude::*;
+use serde::{Serialize, Deserialize};
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameState {
+    // Waiting for somebody to press start
+    Title,
+    // Players join with their gamepads and can fly around
+    Lobby,
+    // Next round is about to begin
+    Countdown,
+    Playing,
+    Paused,
+    // All asteroids of the round are destroyed
+    RoundOver,
+    GameOver,
+}
@@ -0,0 +1,22 @@
+use serde::{Serialize, Deserialize};
+use wasm_bindgen::prelude::*;
+
+// Colors of the rockets, bullets and score totals of each team, also the maximum number of teams
+pub const TEAM_COLORS: [&str; 4] = ["crimson", "royalblue", "seagreen", "darkorange"];
+
+// What happens when a rocket hits a rocket of its own team
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FriendlyFire {
+    // Bullets and rockets of the same team pass through each other
+    Off,
+    // Teammates take damage, but hitting them scores nothing
+    DamageOnly,
+    // Teammates are hit and scored like everybody else
+    #[default]
+    Full,
+}
+
+pub fn team_color( team: usize) -> &'static str {
+    TEAM_COLORS[team % TEAM_COLORS.len()]
+}
@@ -0,0 +1,272 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::utils::{Random, Vector};
+use crate::engine::Area;
+use crate::components::AsteroidSize;
+
+// Waves the game ships with, designers can load their own with Game::load_waves
+const DEFAULT_WAVES: &str = include_str!( "../../assets/waves.json");
+// Limits of hand-edited waves, beyond them the game would be flooded
+const MIN_SAUCER_INTERVAL: f64 = 1.0;
+const MAX_ASTEROIDS: i32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+// Relative weights of the asteroid sizes, they do not have to add up to 1
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SizeMix {
+    pub large: f64,
+    pub medium: f64,
+    pub small: f64,
+}
+
+// Everything a round throws at the players
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Wave {
+    pub asteroids: i32,
+    pub sizes: SizeMix,
+    pub min_speed: f64,
+    pub max_speed: f64,
+    // Edges the asteroids come from, anywhere on the screen when empty
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+    // Seconds between two saucers, None for no saucers at all
+    pub saucer_interval: Option<f64>,
+    pub small_saucer_chance: f64,
+    // Chance that a destroyed asteroid leaves a power-up behind
+    pub power_up_chance: f64,
+}
+
+// How the last wave grows every round after it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Endless {
+    pub asteroids_per_round: i32,
+    pub max_asteroids: i32,
+    pub speed_per_round: f64,
+    pub max_speed: f64,
+    pub saucer_interval_per_round: f64,
+    pub min_saucer_interval: f64,
+    pub small_saucer_chance_per_round: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Waves {
+    pub waves: Vec<Wave>,
+    pub endless: Endless,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WavesError {
+    Empty,
+    Invalid( String),
+}
+
+impl fmt::Display for WavesError {
+    fn fmt( &self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WavesError::Empty => write!( f, "invalid waves: at least one wave is needed"),
+            WavesError::Invalid( reason) => write!( f, "invalid waves: {}", reason),
+        }
+    }
+}
+
+impl Waves {
+    pub fn from_json( json: &str) -> Result<Waves, WavesError> {
+        let waves: Waves = serde_json::from_str( json).map_err( |e| WavesError::Invalid( e.to_string()))?;
+
+        if waves.waves.is_empty() {
+            return Err( WavesError::Empty);
+        }
+
+        for (index, wave) in waves.waves.iter().enumerate() {
+            wave.validate().map_err( |e| WavesError::Invalid( format!( "waves[{}].{}", index, e)))?;
+        }
+
+        waves.endless.validate().map_err( |e| WavesError::Invalid( format!( "endless.{}", e)))?;
+
+        Ok( waves)
+    }
+
+    pub fn to_json( &self) -> String {
+        serde_json::to_string_pretty( self).expect( "waves are always serializable")
+    }
+
+    // Wave of a round, starting with round 1. Rounds after the last wave scale it up.
+    pub fn wave( &self, round: i32) -> Wave {
+        let index = (round.max( 1) - 1) as usize;
+        if let Some( wave) = self.waves.get( index) {
+            return wave.clone();
+        }
+
+        let last = self.waves.last().expect( "waves are never empty");
+        let rounds = (index + 1 - self.waves.len()) as f64;
+        let endless = &self.endless;
+
+        // Grows towards the limits, but never below the last wave itself
+        let max_speed = (last.max_speed + endless.speed_per_round * rounds).min( endless.max_speed).max( last.max_speed);
+
+        Wave {
+            asteroids: (last.asteroids + endless.asteroids_per_round * rounds as i32).min( endless.max_asteroids).max( last.asteroids),
+            min_speed: (last.min_speed + endless.speed_per_round * rounds).min( max_speed),
+            max_speed,
+            saucer_interval: last.saucer_interval.map( |interval| (interval - endless.saucer_interval_per_round * rounds).max( endless.min_saucer_interval).min( interval)),
+            small_saucer_chance: (last.small_saucer_chance + endless.small_saucer_chance_per_round * rounds).min( 1.0),
+            ..last.clone()
+        }
+    }
+}
+
+impl Default for Waves {
+    fn default() -> Self {
+        Waves::from_json( DEFAULT_WAVES).expect( "built-in waves are valid")
+    }
+}
+
+// Errors name the field, the caller adds where it is
+fn at_least( field: &str, value: f64, min: f64) -> Result<(), String> {
+    if value < min {
+        return Err( format!( "{} must be at least {}, not {}", field, min, value));
+    }
+
+    Ok(())
+}
+
+fn at_most( field: &str, value: f64, max: f64) -> Result<(), String> {
+    if value > max {
+        return Err( format!( "{} must be at most {}, not {}", field, max, value));
+    }
+
+    Ok(())
+}
+
+fn asteroid_count( field: &str, value: i32) -> Result<(), String> {
+    if !(0..=MAX_ASTEROIDS).contains( &value) {
+        return Err( format!( "{} must be between 0 and {}, not {}", field, MAX_ASTEROIDS, value));
+    }
+
+    Ok(())
+}
+
+fn chance( field: &str, value: f64) -> Result<(), String> {
+    if !(0.0..=1.0).contains( &value) {
+        return Err( format!( "{} must be between 0 and 1, not {}", field, value));
+    }
+
+    Ok(())
+}
+
+impl Endless {
+    fn validate( &self) -> Result<(), String> {
+        at_least( "asteroids_per_round", self.asteroids_per_round as f64, 0.0)?;
+        asteroid_count( "max_asteroids", self.max_asteroids)?;
+        at_least( "speed_per_round", self.speed_per_round, 0.0)?;
+        at_least( "max_speed", self.max_speed, 0.0)?;
+        at_least( "saucer_interval_per_round", self.saucer_interval_per_round, 0.0)?;
+        at_least( "min_saucer_interval", self.min_saucer_interval, MIN_SAUCER_INTERVAL)?;
+        at_least( "small_saucer_chance_per_round", self.small_saucer_chance_per_round, 0.0)
+    }
+}
+
+impl Wave {
+    fn validate( &self) -> Result<(), String> {
+        asteroid_count( "asteroids", self.asteroids)?;
+        at_least( "sizes.large", self.sizes.large, 0.0)?;
+        at_least( "sizes.medium", self.sizes.medium, 0.0)?;
+        at_least( "sizes.small", self.sizes.small, 0.0)?;
+        if self.sizes.large + self.sizes.medium + self.sizes.small <= 0.0 {
+            return Err( "sizes must have at least one weight above 0".to_string());
+        }
+
+        at_least( "min_speed", self.min_speed, 0.0)?;
+        at_most( "min_speed", self.min_speed, self.max_speed)?;
+        if let Some( interval) = self.saucer_interval {
+            at_least( "saucer_interval", interval, MIN_SAUCER_INTERVAL)?;
+        }
+
+        chance( "small_saucer_chance", self.small_saucer_chance)?;
+        chance( "power_up_chance", self.power_up_chance)
+    }
+
+    pub fn random_size( &self, rng: &mut Random) -> AsteroidSize {
+        let sizes = &self.sizes;
+        let total = sizes.large + sizes.medium + sizes.small;
+        let pick = rng.random_number() * total;
+
+        if pick < sizes.small {
+            AsteroidSize::Small
+        }
+        else if pick < sizes.small + sizes.medium {
+            AsteroidSize::Medium
+        }
+        else {
+            AsteroidSize::Large
+        }
+    }
+
+    // On one of the edges of the wave, or anywhere
+    pub fn random_position( &self, rng: &mut Random, area: Area) -> Vector {
+        if self.edges.is_empty() {
+            return Vector::new( rng.random_number_max( area.width), rng.random_number_max( area.height));
+        }
+
+        let edge = self.edges[(rng.random_number() * self.edges.len() as f64) as usize % self.edges.len()];
+        match edge {
+            Edge::Top => Vector::new( rng.random_number_max( area.width), 0.0),
+            Edge::Bottom => Vector::new( rng.random_number_max( area.width), area.height),
+            Edge::Left => Vector::new( 0.0, rng.random_number_max( area.height)),
+            Edge::Right => Vector::new( area.width, rng.random_number_max( area.height)),
+        }
+    }
+
+    // In a random direction, with a speed in the range of the wave
+    pub fn random_speed( &self, rng: &mut Random) -> Vector {
+        let speed = self.min_speed + rng.random_number() * (self.max_speed - self.min_speed).max( 0.0);
+        let angle = rng.random_number() * 2.0 * std::f64::consts::PI;
+
+        Vector::new( angle.cos() * speed, angle.sin() * speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    // Built-in waves with one field replaced
+    fn edited( path: &[&str], value: Value) -> Result<Waves, WavesError> {
+        let mut waves: Value = serde_json::from_str( DEFAULT_WAVES).unwrap();
+        let field = path.iter().fold( &mut waves, |value, key| match key.parse::<usize>() {
+            Ok( index) => &mut value[index],
+            Err( _) => &mut value[*key],
+        });
+        *field = value;
+
+        Waves::from_json( &waves.to_string())
+    }
+
+    fn invalid( reason: &str) -> Result<Waves, WavesError> {
+        Err( WavesError::Invalid( reason.to_string()))
+    }
+
+    #[test]
+    fn built_in_waves_are_valid() {
+        assert!( Waves::from_json( DEFAULT_WAVES).is_ok());
+        assert_eq!( edited( &["waves"], json!( [])), Err( WavesError::Empty));
+    }
+
+    #[test]
+    fn out_of_range_fields_are_named() {
+        assert_eq!( edited( &["waves", "1", "saucer_interval"], json!( 0.0)), invalid( "waves[1].saucer_interval must be at least 1, not 0"));
+        assert_eq!( edited( &["waves", "0", "asteroids"], json!( -2)), invalid( "waves[0].asteroids must be between 0 and 100, not -2"));
+        assert_eq!( edited( &["waves", "0", "min_speed"], json!( 60.0)), invalid( "waves[0].min_speed must be at most 50, not 60"));
+        assert_eq!( edited( &["waves", "2", "power_up_chance"], json!( 1.5)), invalid( "waves[2].power_up_chance must be between 0 and 1, not 1.5"));
+        assert_eq!( edited( &["endless", "min_saucer_interval"], json!( -1.0)), invalid( "endless.min_saucer_interval must be at least 1, not -1"));
+        assert!( edited( &["waves", "0", "saucer_interval"], Value::Null).is_ok());
+    }
+}
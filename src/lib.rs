@@ -1,12 +1,16 @@
-mod utils;
-mod engine;
-mod components;
+// Explicit returns and spelled out field initializers are the house style, and Game::new takes every sprite
+// image as its own argument
+#![allow(clippy::needless_return, clippy::redundant_field_names, clippy::too_many_arguments)]
+
+pub mod utils;
+pub mod engine;
+pub mod components;
+pub mod net;
 
 use wasm_bindgen::prelude::*;
 use web_sys::{window, HtmlImageElement};
 
 use crate::engine::*;
-use crate::components::*;
 
 pub fn clone_sprite( image: &HtmlImageElement) -> HtmlImageElement{
     let document = window().unwrap().document().unwrap();
@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+use crate::engine::{GamepadState, ReplayCommand, WorldSnapshot};
+use crate::net::{NetMessage, Transport};
+
+// Received snapshots kept as base for deltas, more than the server may refer to within its history
+const RECEIVED_SNAPSHOTS: usize = 64;
+
+// Client side of an authoritative server, see GameServer
+pub struct ServerConnection {
+    transport: Box<dyn Transport>,
+    player: Option<usize>,
+    received: VecDeque<(u32, WorldSnapshot)>,
+}
+
+impl ServerConnection {
+    pub fn new( transport: Box<dyn Transport>) -> Self {
+        ServerConnection {
+            transport,
+            player: None,
+            received: VecDeque::new(),
+        }
+    }
+
+    // rocket_index of this client, None until the first snapshot arrived
+    pub fn player( &self) -> Option<usize> {
+        self.player
+    }
+
+    pub fn latest_tick( &self) -> Option<u32> {
+        self.received.back().map( |(tick, _)| *tick)
+    }
+
+    pub fn send_input( &mut self, state: GamepadState) {
+        self.transport.send( &NetMessage::Input { state }.encode());
+    }
+
+    pub fn send_command( &mut self, command: ReplayCommand) {
+        self.transport.send( &NetMessage::Command { command }.encode());
+    }
+
+    // Newest world received since the last call, snapshots arriving out of order are dropped
+    pub fn receive( &mut self) -> Option<WorldSnapshot> {
+        let mut newest = None;
+
+        for packet in self.transport.receive() {
+            let (player, tick, world) = match NetMessage::decode( &packet) {
+                Some( NetMessage::FullSnapshot { player, tick, world }) => (player, tick, world),
+                Some( NetMessage::DeltaSnapshot { player, tick, base_tick, delta }) => {
+                    let Some( (_, base)) = self.received.iter().find( |(received, _)| *received == base_tick) else {
+                        continue;
+                    };
+                    let Ok( world) = delta.apply( base) else {
+                        continue;
+                    };
+
+                    (player, tick, world)
+                }
+                _ => continue,
+            };
+
+            if self.latest_tick().is_some_and( |latest| tick <= latest) {
+                continue;
+            }
+
+            self.player = Some( player);
+            self.received.push_back( (tick, world.clone()));
+            while self.received.len() > RECEIVED_SNAPSHOTS {
+                self.received.pop_front();
+            }
+
+            self.transport.send( &NetMessage::Ack { tick }.encode());
+            newest = Some( world);
+        }
+
+        return newest;
+    }
+}
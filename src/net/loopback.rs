@@ -0,0 +1,170 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use crate::utils::Random;
+use crate::net::Transport;
+
+// How a link between two endpoints misbehaves. Times are in seconds, rates are probabilities per packet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NetworkConditions {
+    pub latency: f64,
+    // Up to this much is added to the latency of every packet
+    pub jitter: f64,
+    pub loss: f64,
+    pub duplication: f64,
+    // A reordered packet is held back for up to twice the latency, so later packets overtake it
+    pub reordering: f64,
+}
+
+impl NetworkConditions {
+    pub fn perfect() -> Self {
+        NetworkConditions {
+            latency: 0.0,
+            jitter: 0.0,
+            loss: 0.0,
+            duplication: 0.0,
+            reordering: 0.0,
+        }
+    }
+}
+
+impl Default for NetworkConditions {
+    fn default() -> Self {
+        Self::perfect()
+    }
+}
+
+struct Packet {
+    deliver_at: f64,
+    // Order of sending, delivers packets due at the same time in the order they were sent
+    sequence: u64,
+    data: Vec<u8>,
+}
+
+struct Clock {
+    time: f64,
+    rng: Random,
+    sequence: u64,
+}
+
+type Inbox = Rc<RefCell<Vec<Packet>>>;
+
+// Simulated network for native tests, time only passes when the test calls advance.
+// Same seed and same traffic give the same losses and delays.
+#[derive(Clone)]
+pub struct LoopbackNetwork {
+    clock: Rc<RefCell<Clock>>,
+}
+
+impl LoopbackNetwork {
+    pub fn new( seed: u64) -> Self {
+        LoopbackNetwork {
+            clock: Rc::new( RefCell::new( Clock { time: 0.0, rng: Random::new( seed), sequence: 0 })),
+        }
+    }
+
+    pub fn time( &self) -> f64 {
+        self.clock.borrow().time
+    }
+
+    pub fn advance( &self, delta_t: f64) {
+        self.clock.borrow_mut().time += delta_t;
+    }
+
+    // Two endpoints, e.g. a client and its connection on the host
+    pub fn connect( &self, conditions: NetworkConditions) -> (LoopbackTransport, LoopbackTransport) {
+        let mut endpoints = self.mesh( 2, conditions);
+        let second = endpoints.pop().unwrap();
+        let first = endpoints.pop().unwrap();
+
+        (first, second)
+    }
+
+    // Endpoints that send every packet to all the others, like the peers of a rollback session behind a relay
+    pub fn mesh( &self, number_of_endpoints: usize, conditions: NetworkConditions) -> Vec<LoopbackTransport> {
+        let inboxes: Vec<Inbox> = (0..number_of_endpoints).map( |_| Rc::new( RefCell::new( vec![]))).collect();
+
+        (0..number_of_endpoints).map( |endpoint| LoopbackTransport {
+            clock: self.clock.clone(),
+            conditions,
+            inbox: inboxes[endpoint].clone(),
+            peers: inboxes.iter().enumerate().filter( |(peer, _)| *peer != endpoint).map( |(_, inbox)| inbox.clone()).collect(),
+            sent: 0,
+            dropped: 0,
+        }).collect()
+    }
+}
+
+pub struct LoopbackTransport {
+    clock: Rc<RefCell<Clock>>,
+    conditions: NetworkConditions,
+    inbox: Inbox,
+    peers: Vec<Inbox>,
+    sent: usize,
+    dropped: usize,
+}
+
+impl LoopbackTransport {
+    pub fn conditions( &self) -> NetworkConditions {
+        self.conditions
+    }
+
+    // Changes the conditions of packets sent from now on, e.g. to simulate a connection dropping out for a while
+    pub fn set_conditions( &mut self, conditions: NetworkConditions) {
+        self.conditions = conditions;
+    }
+
+    // Packets sent to a peer, including the lost ones
+    pub fn sent( &self) -> usize {
+        self.sent
+    }
+
+    pub fn dropped( &self) -> usize {
+        self.dropped
+    }
+
+    fn delivery_time( &self, clock: &mut Clock) -> f64 {
+        let conditions = &self.conditions;
+        let mut delay = conditions.latency + clock.rng.random_number_max( conditions.jitter);
+
+        if clock.rng.random_number() < conditions.reordering {
+            delay += clock.rng.random_number_max( 2.0 * conditions.latency.max( conditions.jitter));
+        }
+
+        clock.time + delay
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn send( &mut self, packet: &[u8]) {
+        let mut clock = self.clock.borrow_mut();
+
+        for peer in &self.peers {
+            self.sent += 1;
+
+            if clock.rng.random_number() < self.conditions.loss {
+                self.dropped += 1;
+                continue;
+            }
+
+            let copies = if clock.rng.random_number() < self.conditions.duplication { 2 } else { 1 };
+            for _ in 0..copies {
+                let deliver_at = self.delivery_time( &mut clock);
+                clock.sequence += 1;
+
+                peer.borrow_mut().push( Packet { deliver_at, sequence: clock.sequence, data: packet.to_vec() });
+            }
+        }
+    }
+
+    fn receive( &mut self) -> Vec<Vec<u8>> {
+        let time = self.clock.borrow().time;
+        let mut inbox = self.inbox.borrow_mut();
+
+        let (mut due, pending): (Vec<Packet>, Vec<Packet>) = inbox.drain( ..).partition( |packet| packet.deliver_at <= time);
+        *inbox = pending;
+
+        due.sort_by( |a, b| a.deliver_at.total_cmp( &b.deliver_at).then( a.sequence.cmp( &b.sequence)));
+
+        return due.into_iter().map( |packet| packet.data).collect();
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Serialize, Deserialize};
+use crate::engine::{GamepadState, ReplayCommand, WorldSnapshot, SnapshotDelta};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum NetMessage {
+    // Input of one player for consecutive frames, starting at first_frame. The last few frames are
+    // repeated in every message, so a lost packet does not lose input.
+    Inputs { player: usize, first_frame: u32, states: Vec<GamepadState> },
+
+    // Client to server: the current input of the client's rocket
+    Input { state: GamepadState },
+    // Client to server: start, pause, resume or reset the match, only the host may, see GameServer
+    Command { command: ReplayCommand },
+    // Client to server: the snapshot of this tick arrived, later ones can be sent as delta against it
+    Ack { tick: u32 },
+
+    // Server to client: the complete world, player is the rocket_index of the receiving client
+    FullSnapshot { player: usize, tick: u32, world: WorldSnapshot },
+    // Server to client: the world as difference to the snapshot of base_tick, which the client acknowledged
+    DeltaSnapshot { player: usize, tick: u32, base_tick: u32, delta: SnapshotDelta },
+}
+
+impl NetMessage {
+    pub fn encode( &self) -> Vec<u8> {
+        serde_json::to_vec( self).expect( "net message is always serializable")
+    }
+
+    // None for packets that are not a valid message, e.g. from a newer client
+    pub fn decode( packet: &[u8]) -> Option<NetMessage> {
+        serde_json::from_slice( packet).ok()
+    }
+}
@@ -0,0 +1,19 @@
+mod transport;
+mod message;
+mod rollback;
+mod websocket;
+mod server;
+mod client;
+mod loopback;
+
+pub use transport::Transport;
+pub use message::NetMessage;
+pub use rollback::RollbackSession;
+pub use rollback::MAX_ROLLBACK_FRAMES;
+pub use websocket::WebSocketTransport;
+pub use server::GameServer;
+pub use server::SNAPSHOT_INTERVAL;
+pub use client::ServerConnection;
+pub use loopback::LoopbackNetwork;
+pub use loopback::LoopbackTransport;
+pub use loopback::NetworkConditions;
@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, VecDeque};
+use crate::engine::{Game, GamepadState, GamepadStates, WorldSnapshot};
+use crate::net::{NetMessage, Transport};
+
+// How far the simulation may run ahead of the input of a remote player (0.5 s at 120 Hz)
+pub const MAX_ROLLBACK_FRAMES: u32 = 60;
+// Number of past local inputs repeated in every message
+const INPUT_REDUNDANCY: u32 = 10;
+
+struct SavedFrame {
+    frame: u32,
+    // World before the frame was simulated
+    world: WorldSnapshot,
+}
+
+// Peer to peer rollback networking. Every peer simulates the whole match, the input of remote
+// players is predicted until it arrives. When a prediction turns out to be wrong, the world is
+// restored to the frame of the misprediction and simulated again with the real input.
+pub struct RollbackSession {
+    transport: Box<dyn Transport>,
+    local_player: usize,
+    number_of_players: usize,
+    // Next frame to simulate
+    frame: u32,
+    confirmed: Vec<BTreeMap<u32, GamepadState>>,
+    // Number of frames from the start, for which the input of the player is known without gaps
+    confirmed_frames: Vec<u32>,
+    // Inputs the simulation used for each frame, to detect mispredictions
+    used: BTreeMap<u32, Vec<GamepadState>>,
+    saved: VecDeque<SavedFrame>,
+    rollbacks: u32,
+}
+
+impl RollbackSession {
+    pub fn new( transport: Box<dyn Transport>, local_player: usize, number_of_players: usize) -> Self {
+        RollbackSession {
+            transport,
+            local_player,
+            number_of_players,
+            frame: 0,
+            confirmed: vec![BTreeMap::new(); number_of_players],
+            confirmed_frames: vec![0; number_of_players],
+            used: BTreeMap::new(),
+            saved: VecDeque::new(),
+            rollbacks: 0,
+        }
+    }
+
+    pub fn frame( &self) -> u32 {
+        self.frame
+    }
+
+    pub fn local_player( &self) -> usize {
+        self.local_player
+    }
+
+    pub fn number_of_players( &self) -> usize {
+        self.number_of_players
+    }
+
+    // Number of times the world had to be corrected so far
+    pub fn rollbacks( &self) -> u32 {
+        self.rollbacks
+    }
+
+    // Simulates the next frame with the local input. Returns false without simulating, when a
+    // remote player is too far behind to predict any further.
+    pub fn advance_frame( &mut self, game: &mut Game, local: GamepadState) -> bool {
+        self.receive( game);
+
+        if self.frame >= self.oldest_unconfirmed_frame() + MAX_ROLLBACK_FRAMES {
+            // Repeat our input, so the others are not waiting for us as well
+            self.send_inputs();
+            return false;
+        }
+
+        let mut local = local;
+        local.rocket_index = self.local_player;
+        self.confirm( self.local_player, self.frame, local);
+        self.send_inputs();
+
+        self.simulate_frame( game, self.frame);
+        self.frame += 1;
+        self.prune();
+
+        return true;
+    }
+
+    fn oldest_unconfirmed_frame( &self) -> u32 {
+        (0..self.number_of_players)
+            .filter( |player| *player != self.local_player)
+            .map( |player| self.confirmed_frames[player])
+            .min()
+            .unwrap_or( self.frame)
+    }
+
+    fn confirm( &mut self, player: usize, frame: u32, state: GamepadState) {
+        self.confirmed[player].insert( frame, state);
+
+        while self.confirmed[player].contains_key( &self.confirmed_frames[player]) {
+            self.confirmed_frames[player] += 1;
+        }
+    }
+
+    // Known input, otherwise the player is expected to keep doing what they did last
+    fn input_of( &self, player: usize, frame: u32) -> GamepadState {
+        let known = self.confirmed[player].get( &frame).or_else( || self.confirmed[player].range( ..frame).next_back().map( |(_, state)| state));
+
+        known.copied().unwrap_or_else( || {
+            let mut state = GamepadState::new();
+            state.rocket_index = player;
+            state
+        })
+    }
+
+    fn simulate_frame( &mut self, game: &mut Game, frame: u32) {
+        let inputs: Vec<GamepadState> = (0..self.number_of_players).map( |player| self.input_of( player, frame)).collect();
+
+        self.saved.push_back( SavedFrame { frame, world: game.world_snapshot() });
+        game.step( &GamepadStates::from( inputs.clone()));
+        self.used.insert( frame, inputs);
+    }
+
+    fn send_inputs( &mut self) {
+        let last_frame = match self.confirmed[self.local_player].keys().next_back() {
+            Some( frame) => *frame,
+            None => return,
+        };
+        let first_frame = (last_frame + 1).saturating_sub( INPUT_REDUNDANCY);
+        let states = (first_frame..=last_frame).map( |frame| self.input_of( self.local_player, frame)).collect();
+
+        let message = NetMessage::Inputs { player: self.local_player, first_frame, states };
+        self.transport.send( &message.encode());
+    }
+
+    fn receive( &mut self, game: &mut Game) {
+        let mut rollback_to: Option<u32> = None;
+
+        for packet in self.transport.receive() {
+            let Some( NetMessage::Inputs { player, first_frame, states }) = NetMessage::decode( &packet) else {
+                continue;
+            };
+
+            if player >= self.number_of_players || player == self.local_player {
+                continue;
+            }
+
+            for (offset, state) in states.into_iter().enumerate() {
+                let frame = first_frame + offset as u32;
+                if self.confirmed[player].contains_key( &frame) {
+                    continue;
+                }
+
+                let mut state = state;
+                state.rocket_index = player;
+                self.confirm( player, frame, state);
+
+                let mispredicted = self.used.get( &frame).is_some_and( |inputs| inputs[player] != state);
+                if mispredicted {
+                    rollback_to = Some( rollback_to.map_or( frame, |earliest| earliest.min( frame)));
+                }
+            }
+        }
+
+        if let Some( frame) = rollback_to {
+            self.rollback( game, frame);
+        }
+    }
+
+    fn rollback( &mut self, game: &mut Game, from: u32) {
+        // Older than the saved frames, can not happen as long as advance_frame waits for the remote players
+        let Some( position) = self.saved.iter().position( |saved| saved.frame == from) else {
+            return;
+        };
+
+        let world = self.saved[position].world.clone();
+        self.saved.truncate( position);
+
+        if game.restore_world( world).is_err() {
+            return;
+        }
+
+        for frame in from..self.frame {
+            self.simulate_frame( game, frame);
+        }
+
+        self.rollbacks += 1;
+    }
+
+    // Forgets frames that can not be corrected anymore
+    fn prune( &mut self) {
+        while self.saved.len() > MAX_ROLLBACK_FRAMES as usize {
+            self.saved.pop_front();
+        }
+
+        let oldest = self.saved.front().map_or( self.frame, |saved| saved.frame);
+        self.used.retain( |frame, _| *frame >= oldest);
+
+        // Keep the last input before the window, it is the prediction for the frames after it
+        for confirmed in self.confirmed.iter_mut() {
+            let last_before = confirmed.range( ..oldest).next_back().map( |(frame, _)| *frame);
+            confirmed.retain( |frame, _| *frame >= oldest || Some( *frame) == last_before);
+        }
+    }
+}
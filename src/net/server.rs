@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+use crate::engine::{Game, GamepadState, GamepadStates, ReplayCommand, WorldSnapshot};
+use crate::net::{NetMessage, Transport};
+
+// Simulation steps between two snapshots sent to the clients (60 per second)
+pub const SNAPSHOT_INTERVAL: u32 = 2;
+// Snapshots kept as base for deltas, a client acknowledging an older one gets a full snapshot
+const SNAPSHOT_HISTORY: usize = 64;
+
+struct RemoteClient {
+    transport: Box<dyn Transport>,
+    player: usize,
+    input: GamepadState,
+    acked_tick: Option<u32>,
+}
+
+// Authoritative server. Only the server simulates, clients send their input and render the snapshots they get back.
+// The client that joined first is the host, only the host starts, pauses and resets the match.
+pub struct GameServer {
+    game: Game,
+    tick: u32,
+    clients: Vec<RemoteClient>,
+    history: VecDeque<(u32, WorldSnapshot)>,
+}
+
+impl GameServer {
+    pub fn new( game: Game) -> Self {
+        GameServer {
+            game,
+            tick: 0,
+            clients: vec![],
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn game( &self) -> &Game {
+        &self.game
+    }
+
+    pub fn tick( &self) -> u32 {
+        self.tick
+    }
+
+    pub fn number_of_clients( &self) -> usize {
+        self.clients.len()
+    }
+
+    // Gives the client a rocket, returns its player index
+    pub fn add_client( &mut self, transport: Box<dyn Transport>, color: String) -> usize {
+        let player = self.game.create_rocket( color);
+
+        let mut input = GamepadState::new();
+        input.rocket_index = player;
+        self.clients.push( RemoteClient { transport, player, input, acked_tick: None });
+
+        return player;
+    }
+
+    pub fn remove_client( &mut self, player: usize) {
+        self.clients.retain( |client| client.player != player);
+        self.game.remove_rocket( player);
+    }
+
+    // One fixed simulation step with the latest input of every client
+    pub fn step( &mut self) {
+        self.receive();
+
+        let states: Vec<GamepadState> = self.clients.iter().map( |client| client.input).collect();
+        self.game.step( &GamepadStates::from( states));
+        self.tick += 1;
+
+        if self.tick.is_multiple_of( SNAPSHOT_INTERVAL) {
+            self.broadcast();
+        }
+    }
+
+    fn receive( &mut self) {
+        let mut commands = vec![];
+
+        for (index, client) in self.clients.iter_mut().enumerate() {
+            for packet in client.transport.receive() {
+                match NetMessage::decode( &packet) {
+                    Some( NetMessage::Input { state }) => {
+                        // Whatever the client claims, it only controls its own rocket and no faster than with a gamepad
+                        client.input = GamepadState {
+                            rocket_index: client.player,
+                            rotate: within( state.rotate, -1.0, 1.0),
+                            thrust: within( state.thrust, 0.0, 1.0),
+                            ..state
+                        };
+                    }
+                    Some( NetMessage::Command { command }) => commands.push( (index == 0, client.player, command)),
+                    Some( NetMessage::Ack { tick }) if tick <= self.tick && client.acked_tick.is_none_or( |acked| tick > acked) => {
+                        client.acked_tick = Some( tick);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for (host, player, command) in commands {
+            self.apply_command( host, player, command);
+        }
+    }
+
+    // Joining and leaving is up to the server, so are the waves and collisions. A client can only
+    // change the team of its own rocket, everything else about the match is up to the host.
+    fn apply_command( &mut self, host: bool, player: usize, command: ReplayCommand) {
+        match command {
+            ReplayCommand::SetTeam { rocket_index, team } if rocket_index == player => { self.game.set_team( rocket_index, team); }
+            ReplayCommand::Start if host => self.game.start(),
+            ReplayCommand::Pause if host => self.game.pause(),
+            ReplayCommand::Resume if host => self.game.resume(),
+            ReplayCommand::EndGame if host => self.game.end_game(),
+            ReplayCommand::Reset { .. } if host => self.game.reset( None),
+            ReplayCommand::SetFriendlyFire { friendly_fire } if host => self.game.set_friendly_fire( friendly_fire),
+            _ => {}
+        }
+    }
+
+    fn broadcast( &mut self) {
+        let world = self.game.world_snapshot();
+
+        for client in self.clients.iter_mut() {
+            let base = client.acked_tick.and_then( |acked| self.history.iter().find( |(tick, _)| *tick == acked));
+
+            let message = match base {
+                Some( (base_tick, base)) => NetMessage::DeltaSnapshot {
+                    player: client.player,
+                    tick: self.tick,
+                    base_tick: *base_tick,
+                    delta: world.delta( base),
+                },
+                None => NetMessage::FullSnapshot { player: client.player, tick: self.tick, world: world.clone() },
+            };
+
+            client.transport.send( &message.encode());
+        }
+
+        self.history.push_back( (self.tick, world));
+        while self.history.len() > SNAPSHOT_HISTORY {
+            self.history.pop_front();
+        }
+    }
+}
+
+// Value limited to the range of a gamepad axis, nothing for values that are not a number at all
+fn within( value: f64, min: f64, max: f64) -> f64 {
+    if value.is_nan() {
+        return 0.0;
+    }
+
+    value.clamp( min, max)
+}
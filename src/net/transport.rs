@@ -0,0 +1,6 @@
+// Unreliable, unordered packet delivery between the peers of a match.
+// Every packet sent is meant for all other peers, implementations may drop, duplicate or reorder packets.
+pub trait Transport {
+    fn send( &mut self, packet: &[u8]);
+    fn receive( &mut self) -> Vec<Vec<u8>>;
+}
@@ -0,0 +1,54 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+use web_sys::{BinaryType, MessageEvent, WebSocket};
+use crate::net::Transport;
+
+// Transport through a WebSocket relay or server, see src/bin/relay.rs
+pub struct WebSocketTransport {
+    socket: WebSocket,
+    received: Rc<RefCell<Vec<Vec<u8>>>>,
+    _on_message: Closure<dyn FnMut( MessageEvent)>,
+}
+
+impl WebSocketTransport {
+    pub fn connect( url: &str) -> Result<WebSocketTransport, JsValue> {
+        let socket = WebSocket::new( url)?;
+        socket.set_binary_type( BinaryType::Arraybuffer);
+
+        let received = Rc::new( RefCell::new( vec![]));
+        let queue = received.clone();
+        let on_message = Closure::<dyn FnMut( MessageEvent)>::new( move |event: MessageEvent| {
+            if let Ok( buffer) = event.data().dyn_into::<js_sys::ArrayBuffer>() {
+                queue.borrow_mut().push( js_sys::Uint8Array::new( &buffer).to_vec());
+            }
+        });
+        socket.set_onmessage( Some( on_message.as_ref().unchecked_ref()));
+
+        Ok( WebSocketTransport {
+            socket,
+            received,
+            _on_message: on_message,
+        })
+    }
+}
+
+impl Transport for WebSocketTransport {
+    fn send( &mut self, packet: &[u8]) {
+        // Packets sent while connecting are lost, like on any unreliable transport
+        if self.socket.ready_state() == WebSocket::OPEN {
+            let _ = self.socket.send_with_u8_array( packet);
+        }
+    }
+
+    fn receive( &mut self) -> Vec<Vec<u8>> {
+        std::mem::take( &mut *self.received.borrow_mut())
+    }
+}
+
+impl Drop for WebSocketTransport {
+    fn drop( &mut self) {
+        self.socket.set_onmessage( None);
+        let _ = self.socket.close();
+    }
+}
@@ -3,5 +3,5 @@ mod random;
 
 pub use vmath::Vector;
 pub use vmath::ZERO;
-pub use random::random_number;
-pub use random::random_number_max;
\ No newline at end of file
+pub use vmath::GRAVITY;
+pub use random::Random;
@@ -1,15 +1,53 @@
-use wasm_bindgen::prelude::*;
+use serde::{Serialize, Deserialize};
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = Math)]
-    fn random() -> f64;
+// Seedable pseudo random number generator (SplitMix64), so a match can be replayed from its seed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Random {
+    state: u64,
 }
 
-pub fn random_number() -> f64 {
-    random() as f64
+impl Random {
+    pub fn new( seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    // Seed from a source that differs between runs
+    pub fn from_entropy() -> Self {
+        Random::new( entropy_seed())
+    }
+
+    pub fn state( &self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64( &mut self) -> u64 {
+        self.state = self.state.wrapping_add( 0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul( 0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul( 0x94D0_49BB_1331_11EB);
+
+        return z ^ (z >> 31);
+    }
+
+    // Uniformly distributed in [0, 1)
+    pub fn random_number( &mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn random_number_max( &mut self, max : f64) -> f64 {
+        self.random_number() * max
+    }
 }
 
-pub fn random_number_max( max : f64) -> f64 {
-    random() as f64 * max
+#[cfg(target_arch = "wasm32")]
+fn entropy_seed() -> u64 {
+    (js_sys::Math::random() * u32::MAX as f64) as u64
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn entropy_seed() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now().duration_since( UNIX_EPOCH).map( |d| d.as_nanos() as u64).unwrap_or( 0) & u32::MAX as u64
 }
@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Vector {
     pub x: f64,
     pub y: f64
@@ -41,6 +43,15 @@ impl Vector {
         Self::new(self.x - other.x, self.y - other.y)
     }        
 
+    // Mirrors the vector at the plane with the given (unit length) normal
+    pub fn reflect(&self, normal: &Self) -> Self {
+        self.sub(&normal.scale(2.0 * self.dot(normal)))
+    }
+
+    pub fn lerp(&self, other: &Self, t: f64) -> Self {
+        Self::new(self.x + (other.x - self.x) * t, self.y + (other.y - self.y) * t)
+    }
+
     pub fn distance(&self, other: &Self) -> f64 {
         ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
     }
@@ -0,0 +1,183 @@
+use canvas_rust_game::engine::{Game, GameState, GamepadState, GamepadStates, NullRenderer, ReplayCommand, FIXED_DELTA_T};
+use canvas_rust_game::net::{GameServer, LoopbackNetwork, NetMessage, NetworkConditions, RollbackSession, ServerConnection, Transport};
+use serde_json::Value;
+
+fn bad_network() -> NetworkConditions {
+    NetworkConditions {
+        latency: 0.05,
+        jitter: 0.03,
+        loss: 0.1,
+        duplication: 0.05,
+        reordering: 0.1,
+    }
+}
+
+// Input that changes every now and then, different for every player
+fn input( player: u32, frame: u32) -> GamepadStates {
+    let mut state = GamepadState::new();
+    state.rotate = ((frame / (40 + player * 10)) % 3) as f64 - 1.0;
+    state.thrust = ((frame / (60 + player * 7)) % 2) as f64;
+    state.fire = (frame / (25 + player * 5)).is_multiple_of( 2);
+
+    GamepadStates::from( vec![state])
+}
+
+// The accumulator depends on the local frame times, everything else has to match
+fn world_json( game: &Game) -> String {
+    let mut world = game.world_snapshot();
+    world.accumulator = 0.0;
+
+    world.to_json()
+}
+
+#[test]
+fn loopback_delays_and_drops_packets() {
+    let network = LoopbackNetwork::new( 1);
+    let (mut a, mut b) = network.connect( NetworkConditions { latency: 0.1, ..NetworkConditions::perfect() });
+
+    a.send( b"hello");
+    assert!( b.receive().is_empty());
+
+    network.advance( 0.1);
+    assert_eq!( b.receive(), vec![b"hello".to_vec()]);
+
+    a.set_conditions( NetworkConditions { loss: 1.0, ..NetworkConditions::perfect() });
+    a.send( b"lost");
+    network.advance( 1.0);
+    assert!( b.receive().is_empty());
+    assert_eq!( a.dropped(), 1);
+}
+
+#[test]
+fn rollback_peers_agree_on_a_bad_network() {
+    let network = LoopbackNetwork::new( 7);
+    let mut games: Vec<Game> = network.mesh( 3, bad_network()).into_iter().enumerate().map( |(player, transport)| {
+        let mut game = Game::headless( 800.0, 600.0, Box::new( NullRenderer), 42);
+        game.start_session( RollbackSession::new( Box::new( transport), player, 3));
+        game
+    }).collect();
+
+    for frame in 0..3000 {
+        network.advance( FIXED_DELTA_T);
+        for (player, game) in games.iter_mut().enumerate() {
+            game.update( FIXED_DELTA_T, &input( player as u32, frame));
+        }
+    }
+
+    // Stop moving and let the last inputs arrive, peers that waited for input catch up
+    let idle = GamepadStates::new();
+    let target = games.iter().map( |game| game.session().unwrap().frame()).max().unwrap() + 300;
+    while games.iter().any( |game| game.session().unwrap().frame() < target) {
+        network.advance( FIXED_DELTA_T);
+        for game in games.iter_mut().filter( |game| game.session().unwrap().frame() < target) {
+            game.update( FIXED_DELTA_T, &idle);
+        }
+    }
+
+    assert!( games.iter().all( |game| game.session().unwrap().rollbacks() > 0));
+    assert_eq!( world_json( &games[0]), world_json( &games[1]));
+    assert_eq!( world_json( &games[0]), world_json( &games[2]));
+}
+
+#[test]
+fn server_client_follows_the_server_on_a_bad_network() {
+    let network = LoopbackNetwork::new( 3);
+    let mut server = GameServer::new( Game::headless( 800.0, 600.0, Box::new( NullRenderer), 5));
+    let mut clients = vec![];
+
+    for color in ["red", "blue"] {
+        let (host_side, client_side) = network.connect( bad_network());
+        server.add_client( Box::new( host_side), color.to_string());
+
+        let mut client = Game::headless( 800.0, 600.0, Box::new( NullRenderer), 0);
+        client.join_server( ServerConnection::new( Box::new( client_side)));
+        clients.push( client);
+    }
+
+    // Lobby -> first round, sent until it made it through the lossy link
+    for frame in 0..3000u32 {
+        if frame.is_multiple_of( 100) {
+            clients[0].start();
+        }
+
+        server.step();
+        network.advance( FIXED_DELTA_T);
+        for (player, client) in clients.iter_mut().enumerate() {
+            client.update( FIXED_DELTA_T, &input( player as u32, frame));
+        }
+    }
+
+    // One more snapshot, then wait until it arrived; tried again when it got lost
+    let idle = GamepadStates::new();
+    for _ in 0..50 {
+        server.step();
+        server.step();
+
+        for _ in 0..30 {
+            network.advance( FIXED_DELTA_T);
+            clients.iter_mut().for_each( |client| client.update( FIXED_DELTA_T, &idle));
+        }
+
+        if clients.iter().all( |client| client.server_connection().unwrap().latest_tick() == Some( server.tick())) {
+            break;
+        }
+    }
+
+    for (player, client) in clients.iter().enumerate() {
+        assert_eq!( client.server_connection().unwrap().player(), Some( player));
+        assert_eq!( client.server_connection().unwrap().latest_tick(), Some( server.tick()));
+        assert_eq!( world_json( client), world_json( server.game()));
+    }
+}
+
+// Rocket of a player in the world of the server
+fn server_rocket( server: &GameServer, player: usize) -> Value {
+    let world: Value = serde_json::from_str( &server.game().world_snapshot().to_json()).unwrap();
+
+    world["entities"]["slots"].as_array().unwrap().iter()
+        .map( |slot| &slot[1]["Rocket"])
+        .find( |rocket| rocket["player"] == player)
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn server_does_not_trust_its_clients() {
+    let network = LoopbackNetwork::new( 1);
+    let mut server = GameServer::new( Game::headless( 800.0, 600.0, Box::new( NullRenderer), 5));
+    let mut clients = vec![];
+
+    for color in ["red", "blue"] {
+        let (host_side, client_side) = network.connect( NetworkConditions::perfect());
+        server.add_client( Box::new( host_side), color.to_string());
+        clients.push( client_side);
+    }
+
+    let mut send = |server: &mut GameServer, player: usize, message: NetMessage| {
+        clients[player].send( &message.encode());
+        network.advance( FIXED_DELTA_T);
+        server.step();
+    };
+
+    // Only the host runs the match
+    send( &mut server, 1, NetMessage::Command { command: ReplayCommand::Start });
+    assert_eq!( server.game().state(), GameState::Title);
+    send( &mut server, 0, NetMessage::Command { command: ReplayCommand::Start });
+    assert_eq!( server.game().state(), GameState::Lobby);
+
+    // Nobody picks the team of somebody else
+    send( &mut server, 1, NetMessage::Command { command: ReplayCommand::SetTeam { rocket_index: 0, team: Some( 1) } });
+    send( &mut server, 1, NetMessage::Command { command: ReplayCommand::SetTeam { rocket_index: 1, team: Some( 1) } });
+    assert_eq!( server_rocket( &server, 0)["team"], Value::Null);
+    assert_eq!( server_rocket( &server, 1)["team"], 1);
+
+    // Nor steers another rocket, or turns faster than a gamepad can
+    let mut state = GamepadState::new();
+    state.rocket_index = 0;
+    state.rotate = 1000.0;
+    send( &mut server, 1, NetMessage::Input { state });
+    assert_eq!( server_rocket( &server, 0)["rotation"], 0.0);
+
+    let rotation = server_rocket( &server, 1)["rotation"].as_f64().unwrap();
+    assert!( rotation > 0.0 && rotation <= 6.0 * FIXED_DELTA_T + 1e-9, "rotated by {}", rotation);
+}
@@ -0,0 +1,98 @@
+use canvas_rust_game::engine::{Game, GamepadState, GamepadStates, NullRenderer, BotDifficulty, Replay, WorldSnapshot, FIXED_DELTA_T};
+
+// Input that changes every now and then
+fn input( frame: u32) -> GamepadStates {
+    let mut state = GamepadState::new();
+    state.rotate = ((frame / 45) % 3) as f64 - 1.0;
+    state.thrust = ((frame / 70) % 2) as f64;
+    state.fire = (frame / 20).is_multiple_of( 2);
+
+    GamepadStates::from( vec![state])
+}
+
+// Display refresh rates of different machines, including the occasional hitch
+fn frame_time( frame: u32) -> f64 {
+    [1.0 / 60.0, 1.0 / 144.0, 1.0 / 30.0, 0.001, 0.1][(frame % 5) as usize]
+}
+
+// A match with a player and two bots that is well under way
+fn running_game( seed: u32) -> Game {
+    let mut game = Game::headless( 800.0, 600.0, Box::new( NullRenderer), seed);
+    game.create_rocket( "red".to_string());
+    game.add_bot( BotDifficulty::Medium);
+    game.add_bot( BotDifficulty::Hard);
+    game.start();
+    game.start();
+
+    for frame in 0..600 {
+        game.update( FIXED_DELTA_T, &input( frame));
+    }
+
+    game
+}
+
+#[test]
+fn restored_snapshot_continues_like_the_original() {
+    let mut original = running_game( 7);
+    let snapshot = WorldSnapshot::from_json( &original.world_snapshot().to_json()).unwrap();
+
+    let mut restored = Game::headless( 800.0, 600.0, Box::new( NullRenderer), 99);
+    restored.restore_world( snapshot).unwrap();
+
+    for frame in 0..1200 {
+        original.update( frame_time( frame), &input( frame));
+        restored.update( frame_time( frame), &input( frame));
+    }
+
+    assert_eq!( restored.world_snapshot().to_json(), original.world_snapshot().to_json());
+}
+
+#[test]
+fn replay_ends_in_the_recorded_world() {
+    let mut game = running_game( 11);
+    game.start_recording();
+
+    for frame in 0..1200 {
+        if frame == 300 {
+            game.add_bot( BotDifficulty::Easy);
+        }
+
+        game.update( frame_time( frame), &input( frame));
+    }
+
+    let replay = Replay::from_json( &game.stop_recording().unwrap()).unwrap();
+
+    // Watched on a display with a different refresh rate, live input is ignored
+    let mut viewer = Game::headless( 800.0, 600.0, Box::new( NullRenderer), 3);
+    viewer.start_replay( replay).unwrap();
+    while viewer.is_replaying() {
+        viewer.update( 1.0 / 75.0, &input( 0));
+    }
+
+    assert_eq!( viewer.world_snapshot().to_json(), game.world_snapshot().to_json());
+}
+
+#[test]
+fn replay_plays_at_the_recorded_pace() {
+    let mut game = running_game( 5);
+    game.start_recording();
+
+    // Two seconds at 60 Hz
+    for frame in 0..120 {
+        game.update( 1.0 / 60.0, &input( frame));
+    }
+
+    let replay = Replay::from_json( &game.stop_recording().unwrap()).unwrap();
+
+    // Also two seconds at 144 Hz
+    let mut viewer = Game::headless( 800.0, 600.0, Box::new( NullRenderer), 5);
+    viewer.start_replay( replay).unwrap();
+
+    let mut display_frames = 0;
+    while viewer.is_replaying() {
+        viewer.update( 1.0 / 144.0, &input( 0));
+        display_frames += 1;
+    }
+
+    assert!( (287..=290).contains( &display_frames), "{} display frames", display_frames);
+}